@@ -0,0 +1,510 @@
+// bech32 (BIP173) and bech32m (BIP350) codec
+// data is 5bit values, each element is 0 to 31
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+const SEPARATOR: char = '1';
+pub const CHECKSUM_LENGTH: usize = 6;
+pub const MAX_LENGTH: usize = 90;
+pub const MAX_VERSION: u8 = 31;
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Variant {
+    Bech32,
+    Bech32m,
+}
+
+impl Variant {
+    fn constant(self) -> u32 {
+        match self {
+            Variant::Bech32 => 1,
+            Variant::Bech32m => 0x2bc8_30a3,
+        }
+    }
+
+    fn from_residue(residue: u32) -> Option<Variant> {
+        match residue {
+            1 => Some(Variant::Bech32),
+            0x2bc8_30a3 => Some(Variant::Bech32m),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Variant::Bech32 => "bech32",
+            Variant::Bech32m => "bech32m",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Variant, String> {
+        match name {
+            "bech32" => Ok(Variant::Bech32),
+            "bech32m" => Ok(Variant::Bech32m),
+            _ => Err(format!("unknown variant {:?}", name)),
+        }
+    }
+}
+
+/// address version -> checksum variant, bit N is set when version N uses bech32m
+/// bc4py has no fixed rule like BIP350 witness v1+, so new versions are enabled explicitly
+/// default is empty (all bech32), existing addresses keep valid
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct VariantMap(u32);
+
+impl VariantMap {
+    /// map which versions use bech32m
+    pub fn new(bech32m_versions: &[u8]) -> Result<Self, String> {
+        let mut bits = 0u32;
+        for ver in bech32m_versions.iter() {
+            if MAX_VERSION < *ver {
+                return Err(format!("version is 0 to {} but {}", MAX_VERSION, ver));
+            }
+            bits |= 1 << *ver;
+        }
+        Ok(VariantMap(bits))
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        VariantMap(bits)
+    }
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// versions using bech32m in ascending order
+    pub fn bech32m_versions(self) -> Vec<u8> {
+        (0..=MAX_VERSION).filter(|ver| self.0 >> *ver & 1 == 1).collect()
+    }
+
+    /// checksum variant used by address version
+    pub fn variant(self, ver: u8) -> Variant {
+        if ver <= MAX_VERSION && self.0 >> ver & 1 == 1 {
+            Variant::Bech32m
+        } else {
+            Variant::Bech32
+        }
+    }
+}
+
+pub fn polymod(values: &[u8]) -> u32 {
+    polymod_from(1, values)
+}
+
+// polymod is linear, so polymod(a ^ b) = polymod(a) ^ polymod_from(0, b)
+fn polymod_from(mut chk: u32, values: &[u8]) -> u32 {
+    for value in values.iter() {
+        let top = chk >> 25;
+        chk = (chk & 0x1ff_ffff) << 5 ^ *value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= *generator;
+            }
+        }
+    }
+    chk
+}
+
+pub fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut values = Vec::with_capacity(hrp.len() * 2 + 1);
+    values.extend(hrp.bytes().map(|c| c >> 5));
+    values.push(0);
+    values.extend(hrp.bytes().map(|c| c & 31));
+    values
+}
+
+#[inline]
+pub fn charset_index(c: char) -> Option<u8> {
+    let c = c.to_ascii_lowercase();
+    CHARSET.iter().position(|x| *x as char == c).map(|i| i as u8)
+}
+
+#[inline]
+pub fn charset_char(value: u8) -> char {
+    CHARSET[value as usize] as char
+}
+
+fn check_hrp(hrp: &str) -> Result<(), String> {
+    if hrp.is_empty() || 83 < hrp.len() {
+        return Err(format!("hrp length is 1 to 83 but {}", hrp.len()));
+    }
+    if let Some(c) = hrp.chars().find(|c| *c < '!' || '~' < *c) {
+        return Err(format!("invalid hrp char {:?}", c));
+    }
+    Ok(())
+}
+
+fn create_checksum(hrp: &str, data: &[u8], variant: Variant) -> Vec<u8> {
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0u8; CHECKSUM_LENGTH]);
+    let residue = polymod(&values) ^ variant.constant();
+    (0..CHECKSUM_LENGTH)
+        .map(|i| ((residue >> (5 * (5 - i))) & 31) as u8)
+        .collect()
+}
+
+/// encode hrp and 5bit data to lowercase string
+pub fn encode(hrp: &str, data: &[u8], variant: Variant) -> Result<String, String> {
+    check_hrp(hrp)?;
+    if hrp.chars().any(|c| c.is_ascii_uppercase()) && hrp.chars().any(|c| c.is_ascii_lowercase()) {
+        return Err("mixed case hrp".to_owned());
+    }
+    if let Some(value) = data.iter().find(|value| 31 < **value) {
+        return Err(format!("data is 5bit value but {}", value));
+    }
+    let length = hrp.len() + 1 + data.len() + CHECKSUM_LENGTH;
+    if MAX_LENGTH < length {
+        return Err(format!("too long string {}>{}", length, MAX_LENGTH));
+    }
+    let mut string = String::with_capacity(length);
+    string.push_str(&hrp.to_lowercase());
+    string.push(SEPARATOR);
+    data.iter().for_each(|value| string.push(charset_char(*value)));
+    create_checksum(&hrp.to_lowercase(), data, variant).iter()
+        .for_each(|value| string.push(charset_char(*value)));
+    Ok(string)
+}
+
+/// split string to lowercase hrp and 5bit values include checksum
+/// checksum is not verified
+pub fn split(string: &str) -> Result<(String, Vec<u8>), String> {
+    if MAX_LENGTH < string.len() {
+        return Err(format!("too long string {}>{}", string.len(), MAX_LENGTH));
+    }
+    if string.chars().any(|c| c.is_ascii_uppercase()) && string.chars().any(|c| c.is_ascii_lowercase()) {
+        return Err("mixed case string".to_owned());
+    }
+    let position = string.rfind(SEPARATOR)
+        .ok_or_else(|| "separator not found".to_owned())?;
+    let hrp = string[..position].to_lowercase();
+    check_hrp(&hrp)?;
+    let mut values = Vec::with_capacity(string.len() - position - 1);
+    for (i, c) in string[position + 1..].chars().enumerate() {
+        match charset_index(c) {
+            Some(value) => values.push(value),
+            None => return Err(format!("invalid char {:?} at {}", c, position + 1 + i)),
+        }
+    }
+    if values.len() < CHECKSUM_LENGTH {
+        return Err("too short data".to_owned());
+    }
+    Ok((hrp, values))
+}
+
+/// decode string to hrp, 5bit data and detected variant
+pub fn decode(string: &str) -> Result<(String, Vec<u8>, Variant), String> {
+    let (hrp, mut values) = split(string)?;
+    let mut check = hrp_expand(&hrp);
+    check.extend_from_slice(&values);
+    let variant = Variant::from_residue(polymod(&check))
+        .ok_or_else(|| "invalid checksum".to_owned())?;
+    values.truncate(values.len() - CHECKSUM_LENGTH);
+    Ok((hrp, values, variant))
+}
+
+pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, String> {
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let max = (1u32 << to) - 1;
+    let mut output = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for value in data.iter() {
+        let value = *value as u32;
+        if value >> from != 0 {
+            return Err(format!("invalid data range {}", value));
+        }
+        acc = (acc << from) | value;
+        bits += from;
+        while to <= bits {
+            bits -= to;
+            output.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if 0 < bits {
+            output.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if from <= bits || (acc << (to - bits)) & max != 0 {
+        return Err("invalid padding".to_owned());
+    }
+    Ok(output)
+}
+
+/// (hrp, version, identifier) to address with checksum variant
+/// variant is normally selected by `VariantMap::variant(ver)`
+pub fn encode_address(hrp: &str, ver: u8, identifier: &[u8], variant: Variant) -> Result<String, String> {
+    if MAX_VERSION < ver {
+        return Err(format!("version is 0 to {} but {}", MAX_VERSION, ver));
+    }
+    let mut data = vec![ver];
+    data.extend(convert_bits(identifier, 8, 5, true)?);
+    encode(hrp, &data, variant)
+}
+
+/// address to (hrp, version, identifier, variant)
+/// reject when checksum variant is not match with version by map
+pub fn decode_address(address: &str, map: &VariantMap) -> Result<(String, u8, Vec<u8>, Variant), String> {
+    let (hrp, data, variant) = decode(address)?;
+    if data.is_empty() {
+        return Err("no version".to_owned());
+    }
+    let ver = data[0];
+    if variant != map.variant(ver) {
+        return Err(format!("version {} requires {} but {}",
+                           ver, map.variant(ver).as_str(), variant.as_str()));
+    }
+    let identifier = convert_bits(&data[1..], 5, 8, false)?;
+    Ok((hrp, ver, identifier, variant))
+}
+
+/// result of diagnose(), positions are char index of the string
+#[derive(Default, Debug)]
+pub struct Diagnosis {
+    pub error: Option<String>,
+    pub hrp: Option<String>,
+    pub variant: Option<Variant>,
+    pub mixed_case: bool,
+    pub invalid_chars: Vec<(usize, char)>,
+    pub wrong_hrp: bool,
+    pub error_positions: Vec<usize>,
+    pub suggestion: Option<String>,
+}
+
+/// find likely wrong chars of address and suggest corrected address when unambiguous
+/// checksum detects any 4 errors, so up to 2 errors can be located uniquely
+pub fn diagnose(address: &str, expected_hrp: Option<&str>, map: &VariantMap) -> Diagnosis {
+    let mut diagnosis = Diagnosis {
+        mixed_case: address.chars().any(|c| c.is_ascii_uppercase())
+            && address.chars().any(|c| c.is_ascii_lowercase()),
+        ..Default::default()
+    };
+    // ascii only, so char positions are same as the original
+    let lower = address.to_ascii_lowercase();
+    let expected_hrp = expected_hrp.map(|hrp| hrp.to_lowercase());
+    match decode_address(&lower, map) {
+        Ok((hrp, _ver, _identifier, variant)) => {
+            diagnosis.wrong_hrp = expected_hrp.as_ref().map_or(false, |expected| *expected != hrp);
+            if diagnosis.wrong_hrp {
+                diagnosis.error = Some(format!("hrp is {} but {}", expected_hrp.unwrap(), hrp));
+            } else if diagnosis.mixed_case {
+                diagnosis.error = Some("mixed case string".to_owned());
+                diagnosis.suggestion = Some(lower);
+            }
+            diagnosis.hrp = Some(hrp);
+            diagnosis.variant = Some(variant);
+            return diagnosis;
+        },
+        Err(err) => diagnosis.error = Some(err),
+    }
+
+    let chars: Vec<char> = lower.chars().collect();
+    let original: Vec<char> = address.chars().collect();
+    let separator = match chars.iter().rposition(|c| *c == SEPARATOR) {
+        Some(separator) => separator,
+        None => return diagnosis,
+    };
+    let hrp: String = chars[..separator].iter().collect();
+    diagnosis.wrong_hrp = expected_hrp.as_ref().map_or(false, |expected| *expected != hrp);
+    diagnosis.hrp = Some(hrp.clone());
+    // invalid chars are replaced by zero and located as errors
+    let mut values = Vec::with_capacity(chars.len() - separator - 1);
+    for (position, c) in chars.iter().enumerate().skip(separator + 1) {
+        match charset_index(*c) {
+            Some(value) => values.push(value),
+            None => {
+                // report the char as typed, not lowercased
+                diagnosis.invalid_chars.push((position, original[position]));
+                values.push(0);
+            },
+        }
+    }
+    diagnosis.error_positions = diagnosis.invalid_chars.iter().map(|(position, _)| *position).collect();
+    if check_hrp(&hrp).is_err() || values.len() < CHECKSUM_LENGTH || MAX_LENGTH < chars.len() {
+        return diagnosis;
+    }
+    let data: String = values.iter().map(|value| charset_char(*value)).collect();
+
+    // typo in hrp, checksum is valid with expected hrp
+    if let Some(expected) = expected_hrp.as_ref() {
+        let candidate = format!("{}{}{}", expected, SEPARATOR, data);
+        if diagnosis.wrong_hrp && diagnosis.invalid_chars.is_empty() && decode_address(&candidate, map).is_ok() {
+            diagnosis.suggestion = Some(candidate);
+            return diagnosis;
+        }
+    }
+
+    // corrections with fewest changed chars
+    let invalid: Vec<usize> = diagnosis.invalid_chars.iter()
+        .map(|(position, _)| position - separator - 1)
+        .collect();
+    let mut best: Vec<Vec<(usize, u8)>> = Vec::new();
+    let mut best_changed = 3;
+    for correction in locate_errors(&hrp, &values) {
+        let mut changed: Vec<usize> = correction.iter().map(|(index, _)| *index).collect();
+        changed.extend(invalid.iter());
+        changed.sort();
+        changed.dedup();
+        if best_changed < changed.len() {
+            continue;
+        }
+        let mut corrected = values.clone();
+        correction.iter().for_each(|(index, delta)| corrected[*index] ^= *delta);
+        let candidate: String = corrected.iter().map(|value| charset_char(*value)).collect();
+        if decode_address(&format!("{}{}{}", hrp, SEPARATOR, candidate), map).is_err() {
+            continue;
+        }
+        if changed.len() < best_changed {
+            best.clear();
+            best_changed = changed.len();
+        }
+        best.push(correction);
+    }
+    for correction in best.iter() {
+        for (index, _) in correction.iter() {
+            diagnosis.error_positions.push(separator + 1 + index);
+        }
+    }
+    diagnosis.error_positions.sort();
+    diagnosis.error_positions.dedup();
+    if best.len() == 1 {
+        let mut corrected = values.clone();
+        best[0].iter().for_each(|(index, delta)| corrected[*index] ^= *delta);
+        let candidate: String = corrected.iter().map(|value| charset_char(*value)).collect();
+        diagnosis.suggestion = Some(format!("{}{}{}", hrp, SEPARATOR, candidate));
+    }
+    diagnosis
+}
+
+// all 1 or 2 (index, xor delta) corrections of data which make checksum valid for some variant
+fn locate_errors(hrp: &str, values: &[u8]) -> Vec<Vec<(usize, u8)>> {
+    let mut check = hrp_expand(hrp);
+    check.extend_from_slice(values);
+    let residue = polymod(&check);
+    // syndrome of one delta at each index
+    let length = values.len();
+    let mut singles = Vec::with_capacity(length * 31);
+    let mut delta = vec![0u8; length];
+    for index in 0..length {
+        for value in 1..32u8 {
+            delta[index] = value;
+            singles.push((polymod_from(0, &delta[index..]), index, value));
+        }
+        delta[index] = 0;
+    }
+    let mut by_syndrome = std::collections::HashMap::with_capacity(singles.len());
+    for (syndrome, index, value) in singles.iter() {
+        by_syndrome.entry(*syndrome).or_insert_with(Vec::new).push((*index, *value));
+    }
+    let mut corrections = Vec::new();
+    for variant in [Variant::Bech32, Variant::Bech32m].iter() {
+        let target = residue ^ variant.constant();
+        for (syndrome, index, value) in singles.iter() {
+            if *syndrome == target {
+                corrections.push(vec![(*index, *value)]);
+            }
+            if let Some(pairs) = by_syndrome.get(&(target ^ syndrome)) {
+                for (other, other_value) in pairs.iter() {
+                    if index < other {
+                        corrections.push(vec![(*index, *value), (*other, *other_value)]);
+                    }
+                }
+            }
+        }
+    }
+    corrections
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_and_address() {
+        // BIP173 and BIP350 test vectors
+        for (string, variant) in [
+            ("A12UEL5L", Variant::Bech32),
+            ("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw", Variant::Bech32),
+            ("A1LQFN3A", Variant::Bech32m),
+            ("abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx", Variant::Bech32m),
+        ].iter() {
+            let (hrp, data, decoded) = decode(string).unwrap();
+            assert_eq!(decoded, *variant);
+            assert_eq!(encode(&hrp, &data, decoded).unwrap(), string.to_lowercase());
+        }
+        assert!(decode("A12UEL5l").is_err());
+        assert!(decode("a12uel5m").is_err());
+
+        // default map is all bech32
+        let identifier: Vec<u8> = (0..20).collect();
+        let default = VariantMap::default();
+        assert!((0..=MAX_VERSION).all(|ver| default.variant(ver) == Variant::Bech32));
+        let map = VariantMap::new(&[1, 16, 31]).unwrap();
+        assert_eq!(map.bech32m_versions(), vec![1, 16, 31]);
+        assert_eq!(VariantMap::from_bits(map.bits()), map);
+        assert!(VariantMap::new(&[32]).is_err());
+        for variant in [Variant::Bech32, Variant::Bech32m].iter() {
+            assert_eq!(Variant::from_name(variant.as_str()), Ok(*variant));
+        }
+        assert!(Variant::from_name("bech").is_err());
+        for ver in [0u8, 1, 15, 16, 31].iter() {
+            let address = encode_address("test", *ver, &identifier, map.variant(*ver)).unwrap();
+            let (hrp, decoded_ver, decoded, variant) = decode_address(&address, &map).unwrap();
+            assert_eq!((hrp.as_str(), decoded_ver, decoded), ("test", *ver, identifier.clone()));
+            assert_eq!(variant, map.variant(*ver));
+            // variant not match with default map except bech32 versions
+            assert_eq!(decode_address(&address, &default).is_ok(), variant == Variant::Bech32);
+        }
+        assert!(encode_address("test", 32, &identifier, Variant::Bech32).is_err());
+        // version 0 with bech32m checksum
+        let address = encode_address("test", 0, &identifier, Variant::Bech32m).unwrap();
+        assert!(decode_address(&address, &map).is_err());
+    }
+
+    #[test]
+    fn diagnose_typo() {
+        let identifier: Vec<u8> = (100..120).collect();
+        let address = encode_address("test", 0, &identifier, Variant::Bech32).unwrap();
+        let diagnose = |address: &str, hrp: Option<&str>| diagnose(address, hrp, &VariantMap::default());
+        let typo = |positions: &[usize]| -> String {
+            address.chars().enumerate()
+                .map(|(i, c)| if positions.contains(&i) { if c == 'q' {'p'} else {'q'} } else { c })
+                .collect()
+        };
+        let diagnosis = diagnose(&address, Some("test"));
+        assert!(diagnosis.error.is_none() && diagnosis.suggestion.is_none());
+
+        // one or two wrong chars
+        for positions in [vec![10], vec![7, 30], vec![5, 40]].iter() {
+            let diagnosis = diagnose(&typo(positions), None);
+            assert_eq!(&diagnosis.error_positions, positions);
+            assert_eq!(diagnosis.suggestion.as_ref(), Some(&address));
+        }
+
+        // invalid char, mixed case and hrp
+        let mut invalid = address.clone();
+        invalid.replace_range(12..13, "b");
+        let diagnosis = diagnose(&invalid, None);
+        assert_eq!(diagnosis.invalid_chars, vec![(12, 'b')]);
+        assert_eq!(diagnosis.suggestion.as_ref(), Some(&address));
+        // typed char is reported, positions are kept with non-ascii char
+        let mut invalid = address.to_uppercase();
+        invalid.replace_range(12..13, "B");
+        let diagnosis = diagnose(&invalid, None);
+        assert_eq!(diagnosis.invalid_chars, vec![(12, 'B')]);
+        let mut invalid = address.clone();
+        invalid.replace_range(12..13, "\u{130}");
+        let diagnosis = diagnose(&invalid, None);
+        assert_eq!(diagnosis.invalid_chars, vec![(12, '\u{130}')]);
+        assert_eq!(diagnosis.suggestion.as_ref(), Some(&address));
+        let diagnosis = diagnose(&(address[..8].to_uppercase() + &address[8..]), None);
+        assert!(diagnosis.mixed_case);
+        assert_eq!(diagnosis.suggestion.as_ref(), Some(&address));
+        let diagnosis = diagnose(&address.replacen("test", "tesk", 1), Some("test"));
+        assert!(diagnosis.wrong_hrp);
+        assert_eq!(diagnosis.suggestion.as_ref(), Some(&address));
+        assert!(diagnose(&address, Some("main")).wrong_hrp);
+    }
+}
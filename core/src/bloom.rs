@@ -0,0 +1,115 @@
+use crate::utils::{u32_to_bytes, bytes_to_u32};
+use siphasher::sip::SipHasher24;
+use std::f64::consts::LN_2;
+use std::hash::Hasher;
+
+
+// same limits as BIP37
+pub const MAX_FILTER_SIZE: usize = 36000;  // bytes
+pub const MAX_HASH_FUNCS: u32 = 50;
+// seed of n-th hash function
+const HASH_SEED_STEP: u64 = 0xFBA4_C795;
+
+
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    hash_funcs: u32,
+    tweak: u32,
+}
+
+impl BloomFilter {
+    /// optimal size for elements count and false positive rate
+    pub fn new(elements: usize, fp_rate: f64, tweak: u32) -> Result<Self, String> {
+        if !(0.0 < fp_rate && fp_rate < 1.0) {
+            return Err(format!("fp_rate is 0.0 to 1.0 but {}", fp_rate));
+        }
+        let elements = elements.max(1) as f64;
+        let size = (-1.0 / LN_2.powi(2) * elements * fp_rate.ln() / 8.0) as usize;
+        let size = size.max(1).min(MAX_FILTER_SIZE);
+        let hash_funcs = (size as f64 * 8.0 / elements * LN_2) as u32;
+        let hash_funcs = hash_funcs.max(1).min(MAX_HASH_FUNCS);
+        Ok(BloomFilter {bits: vec![0u8; size], hash_funcs, tweak})
+    }
+
+    pub fn size(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn hash_funcs(&self) -> u32 {
+        self.hash_funcs
+    }
+
+    pub fn tweak(&self) -> u32 {
+        self.tweak
+    }
+
+    #[inline]
+    fn bit_index(&self, n: u32, data: &[u8]) -> usize {
+        let seed = n as u64 * HASH_SEED_STEP + self.tweak as u64;
+        let mut hasher = SipHasher24::new_with_keys(seed, 0);
+        hasher.write(data);
+        (hasher.finish() % (self.bits.len() as u64 * 8)) as usize
+    }
+
+    pub fn insert(&mut self, data: &[u8]) {
+        for n in 0..self.hash_funcs {
+            let index = self.bit_index(n, data);
+            self.bits[index >> 3] |= 1 << (index & 7);
+        }
+    }
+
+    pub fn contains(&self, data: &[u8]) -> bool {
+        (0..self.hash_funcs).all(|n| {
+            let index = self.bit_index(n, data);
+            self.bits[index >> 3] & (1 << (index & 7)) != 0
+        })
+    }
+
+    // [hash_funcs u32][tweak u32][size u32][bits]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(12 + self.bits.len());
+        buf.extend_from_slice(&u32_to_bytes(self.hash_funcs));
+        buf.extend_from_slice(&u32_to_bytes(self.tweak));
+        buf.extend_from_slice(&u32_to_bytes(self.bits.len() as u32));
+        buf.extend_from_slice(self.bits.as_slice());
+        buf
+    }
+
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, String> {
+        if buf.len() < 12 {
+            return Err("bloom filter is too short".to_owned());
+        }
+        let hash_funcs = bytes_to_u32(&buf[0..4]);
+        let tweak = bytes_to_u32(&buf[4..8]);
+        let size = bytes_to_u32(&buf[8..12]) as usize;
+        if hash_funcs == 0 || MAX_HASH_FUNCS < hash_funcs {
+            return Err(format!("hash_funcs is 1 to {} but {}", MAX_HASH_FUNCS, hash_funcs));
+        }
+        if size == 0 || MAX_FILTER_SIZE < size {
+            return Err(format!("size is 1 to {} but {}", MAX_FILTER_SIZE, size));
+        }
+        if buf.len() != 12 + size {
+            return Err(format!("bloom filter length is not match {}!={}", buf.len(), 12 + size));
+        }
+        Ok(BloomFilter {bits: buf[12..].to_vec(), hash_funcs, tweak})
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_contains_and_fp_rate() {
+        let mut bloom = BloomFilter::new(1000, 0.01, 7).unwrap();
+        for i in 0..1000u32 {
+            bloom.insert(&u32_to_bytes(i));
+        }
+        let bloom = BloomFilter::from_bytes(&bloom.to_bytes()).unwrap();
+        assert!((0..1000u32).all(|i| bloom.contains(&u32_to_bytes(i))));
+        let false_positive = (1000..11000u32).filter(|i| bloom.contains(&u32_to_bytes(*i))).count();
+        assert!(false_positive < 200, "false positive {}/10000", false_positive);
+        assert!(BloomFilter::new(10, 1.5, 0).is_err());
+    }
+}
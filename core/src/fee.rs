@@ -0,0 +1,204 @@
+use bigint::U256;
+use std::collections::HashMap;
+
+
+// price bucket bounds grow by this ratio
+const BUCKET_SPACING: f64 = 1.1;
+// max price is u32
+const BUCKET_COUNT: usize = 234;
+// track confirm blocks up to this
+pub const MAX_TARGET_BLOCKS: u32 = 48;
+// old data fade out every block
+const DECAY: f64 = 0.998;
+// required confirmed ratio within target
+const SUCCESS_THRESHOLD: f64 = 0.85;
+// required data points to judge bucket range
+// about 0.1 tx per block over the decay window (1 / (1 - DECAY) blocks)
+const SUFFICIENT_TXS: f64 = 50.0;
+
+
+#[inline]
+fn bucket_index(price: u32) -> usize {
+    if price <= 1 {
+        return 0;
+    }
+    let index = ((price as f64).ln() / BUCKET_SPACING.ln()) as usize;
+    index.min(BUCKET_COUNT - 1)
+}
+
+#[inline]
+fn bucket_price(index: usize) -> u32 {
+    // lower bound of bucket
+    let price = BUCKET_SPACING.powi(index as i32).ceil();
+    if price < u32::max_value() as f64 {
+        price as u32
+    } else {
+        u32::max_value()
+    }
+}
+
+
+pub struct FeeEstimator {
+    // incremented by each connected block
+    height: u32,
+    // tracked unconfirmed tx -> (bucket, entry height)
+    tracked: HashMap<U256, (usize, u32)>,
+    // bucket -> decayed count confirmed by (blocks - 1)
+    confirmed: Vec<Vec<f64>>,
+    // bucket -> decayed count of all confirmed
+    total: Vec<f64>,
+    // bucket -> decayed count removed without confirm after (blocks - 1)
+    failed: Vec<Vec<f64>>,
+}
+
+impl FeeEstimator {
+    pub fn new() -> Self {
+        FeeEstimator {
+            height: 0,
+            tracked: HashMap::new(),
+            confirmed: vec![vec![0.0; MAX_TARGET_BLOCKS as usize]; BUCKET_COUNT],
+            total: vec![0.0; BUCKET_COUNT],
+            failed: vec![vec![0.0; MAX_TARGET_BLOCKS as usize]; BUCKET_COUNT],
+        }
+    }
+
+    /// new tx is accepted to pool
+    pub fn process_entry(&mut self, hash: &U256, price: u32) {
+        self.tracked.insert(hash.clone(), (bucket_index(price), self.height));
+    }
+
+    /// tx is removed from pool without confirm (evicted, expired or replaced)
+    /// counted as failure of targets it was waiting for
+    pub fn process_removal(&mut self, hash: &U256) {
+        let (bucket, entry_height) = match self.tracked.remove(hash) {
+            Some(tracked) => tracked,
+            None => return,
+        };
+        // removed in same block, no target is judged
+        let blocks = (self.height - entry_height).min(MAX_TARGET_BLOCKS);
+        if 0 < blocks {
+            self.failed[bucket][blocks as usize - 1] += 1.0;
+        }
+    }
+
+    /// tx is removed by hand, unknown it would be confirmed or not
+    pub fn forget(&mut self, hash: &U256) {
+        self.tracked.remove(hash);
+    }
+
+    /// new block connected with included tx hashs
+    pub fn process_block(&mut self, hashs: &[U256]) {
+        self.height += 1;
+
+        // fade out old data
+        for bucket in 0..BUCKET_COUNT {
+            self.confirmed[bucket].iter_mut().for_each(|count| *count *= DECAY);
+            self.failed[bucket].iter_mut().for_each(|count| *count *= DECAY);
+            self.total[bucket] *= DECAY;
+        }
+
+        // record confirmed blocks
+        for hash in hashs.iter() {
+            let (bucket, entry_height) = match self.tracked.remove(hash) {
+                Some(tracked) => tracked,
+                None => continue,
+            };
+            let blocks = (self.height - entry_height).max(1).min(MAX_TARGET_BLOCKS);
+            self.confirmed[bucket][blocks as usize - 1] += 1.0;
+            self.total[bucket] += 1.0;
+        }
+    }
+
+    /// return lowest price confirmed within target blocks
+    /// txs removed or still waiting after target blocks are counted as failure
+    pub fn estimate(&self, target_blocks: u32) -> Option<u32> {
+        if target_blocks == 0 || MAX_TARGET_BLOCKS < target_blocks {
+            return None;
+        }
+        let target = target_blocks as usize;
+
+        // waiting txs already passed the target
+        let mut outstanding = vec![0.0; BUCKET_COUNT];
+        for (bucket, entry_height) in self.tracked.values() {
+            if target_blocks <= self.height - entry_height {
+                outstanding[*bucket] += 1.0;
+            }
+        }
+
+        // group buckets from high price until enough data
+        let mut best = None;
+        let mut hit = 0.0;
+        let mut total = 0.0;
+        for bucket in (0..BUCKET_COUNT).rev() {
+            hit += self.confirmed[bucket][..target].iter().sum::<f64>();
+            total += self.total[bucket] + self.failed[bucket][target - 1..].iter().sum::<f64>() + outstanding[bucket];
+            if total < SUFFICIENT_TXS {
+                continue;
+            }
+            if hit / total < SUCCESS_THRESHOLD {
+                break;
+            }
+            best = Some(bucket);
+            hit = 0.0;
+            total = 0.0;
+        }
+        best.map(bucket_price)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashs(range: std::ops::Range<u64>) -> Vec<U256> {
+        range.map(U256::from).collect()
+    }
+
+    #[test]
+    fn estimate_fast_confirmed_price() {
+        let mut estimator = FeeEstimator::new();
+        // price 100 txs confirmed next block, price 2 txs wait 10 blocks
+        for round in 0..20u64 {
+            let high = hashs(round * 10..round * 10 + 5);
+            let low = hashs(1000 + round * 10..1000 + round * 10 + 5);
+            high.iter().for_each(|hash| estimator.process_entry(hash, 100));
+            low.iter().for_each(|hash| estimator.process_entry(hash, 2));
+            estimator.process_block(&high);
+            for _ in 0..9 {
+                estimator.process_block(&[]);
+            }
+            estimator.process_block(&low);
+        }
+        assert_eq!(estimator.estimate(1), Some(bucket_price(bucket_index(100))));
+        assert_eq!(estimator.estimate(MAX_TARGET_BLOCKS), Some(bucket_price(bucket_index(2))));
+        assert_eq!(estimator.estimate(0), None);
+    }
+
+    #[test]
+    fn outstanding_and_removed_are_failure() {
+        // few confirmed txs are not enough
+        let mut estimator = FeeEstimator::new();
+        let txs = hashs(0..10);
+        txs.iter().for_each(|hash| estimator.process_entry(hash, 1));
+        estimator.process_block(&txs[..1]);
+        assert_eq!(estimator.estimate(1), None);
+
+        // most txs never confirm in time
+        let mut estimator = FeeEstimator::new();
+        let txs = hashs(0..100);
+        txs.iter().for_each(|hash| estimator.process_entry(hash, 1));
+        estimator.process_block(&txs[..10]);
+        assert_eq!(estimator.estimate(1), None);
+        // expired txs stay failure after removed
+        estimator.process_block(&[]);
+        txs[10..].iter().for_each(|hash| estimator.process_removal(hash));
+        assert_eq!(estimator.estimate(1), None);
+        // manual removal is no data
+        let mut estimator = FeeEstimator::new();
+        txs.iter().for_each(|hash| estimator.process_entry(hash, 1));
+        estimator.process_block(&txs[..60]);
+        txs[60..].iter().for_each(|hash| estimator.forget(hash));
+        assert_eq!(estimator.estimate(1), Some(bucket_price(0)));
+    }
+}
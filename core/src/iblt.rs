@@ -0,0 +1,246 @@
+use crate::utils::{u32_to_bytes, bytes_to_u32};
+use siphasher::sip::SipHasher24;
+use std::hash::Hasher;
+use std::collections::HashSet;
+
+
+// each key is stored to this number of cells (one per sub table)
+const HASH_COUNT: usize = 3;
+// checksum key to find pure cells
+const CHECK_KEY: (u64, u64) = (0x6263_3470_795f_6962, 0x6c74_5f63_6865_636b);
+// serialized cell = count 4b + key_sum 32b + hash_sum 8b
+const CELL_SIZE: usize = 4 + 32 + 8;
+// limit of peer's sketch
+pub const MAX_CELLS: usize = 300000;
+
+
+#[inline]
+fn siphash(k0: u64, k1: u64, key: &[u8; 32]) -> u64 {
+    let mut hasher = SipHasher24::new_with_keys(k0, k1);
+    hasher.write(key);
+    hasher.finish()
+}
+
+#[derive(Clone, Default)]
+struct Cell {
+    count: i32,
+    key_sum: [u8; 32],
+    hash_sum: u64,
+}
+
+impl Cell {
+    fn toggle(&mut self, key: &[u8; 32], check: u64, sign: i32) {
+        self.count = self.count.wrapping_add(sign);
+        self.key_sum.iter_mut().zip(key.iter()).for_each(|(a, b)| *a ^= *b);
+        self.hash_sum ^= check;
+    }
+
+    fn is_empty(&self) -> bool {
+        self.count == 0 && self.hash_sum == 0 && self.key_sum.iter().all(|b| *b == 0)
+    }
+
+    // only one key is left in the cell
+    fn is_pure(&self) -> bool {
+        (self.count == 1 || self.count == -1)
+            && self.hash_sum == siphash(CHECK_KEY.0, CHECK_KEY.1, &self.key_sum)
+    }
+}
+
+/// invertible bloom lookup table of 32 bytes keys
+/// subtract peer's table and decode to get symmetric difference
+/// decode succeeds when difference is smaller than about 2/3 of cells
+#[derive(Clone)]
+pub struct Iblt {
+    cells: Vec<Cell>,
+}
+
+impl Iblt {
+    pub fn new(cells: usize) -> Self {
+        // round up to sub tables
+        let sub_size = ((cells + HASH_COUNT - 1) / HASH_COUNT).max(1);
+        Iblt {cells: vec![Cell::default(); sub_size * HASH_COUNT]}
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn indexes(&self, key: &[u8; 32]) -> [usize; HASH_COUNT] {
+        let sub_size = self.cells.len() / HASH_COUNT;
+        let mut indexes = [0usize; HASH_COUNT];
+        for (i, index) in indexes.iter_mut().enumerate() {
+            let hash = siphash(i as u64, 0, key);
+            *index = i * sub_size + (hash % sub_size as u64) as usize;
+        }
+        indexes
+    }
+
+    fn toggle(&mut self, key: &[u8; 32], sign: i32) {
+        let check = siphash(CHECK_KEY.0, CHECK_KEY.1, key);
+        for index in self.indexes(key).iter() {
+            self.cells[*index].toggle(key, check, sign);
+        }
+    }
+
+    pub fn insert(&mut self, key: &[u8; 32]) {
+        self.toggle(key, 1);
+    }
+
+    pub fn erase(&mut self, key: &[u8; 32]) {
+        self.toggle(key, -1);
+    }
+
+    /// cell by cell difference, keys only in self are positive
+    pub fn subtract(&self, other: &Iblt) -> Result<Iblt, String> {
+        if self.cells.len() != other.cells.len() {
+            return Err(format!("cells size is not match {}!={}", self.cells.len(), other.cells.len()));
+        }
+        let mut diff = self.clone();
+        for (cell, other) in diff.cells.iter_mut().zip(other.cells.iter()) {
+            cell.count = cell.count.wrapping_sub(other.count);
+            cell.key_sum.iter_mut().zip(other.key_sum.iter()).for_each(|(a, b)| *a ^= *b);
+            cell.hash_sum ^= other.hash_sum;
+        }
+        Ok(diff)
+    }
+
+    /// peel pure cells and return (positive keys, negative keys)
+    /// error when table is too small for the difference or crafted (key peeled twice)
+    pub fn decode(mut self) -> Result<(Vec<[u8; 32]>, Vec<[u8; 32]>), String> {
+        let mut positive = Vec::new();
+        let mut negative = Vec::new();
+        let mut peeled: HashSet<[u8; 32]> = HashSet::new();
+        let mut pending: Vec<usize> = (0..self.cells.len())
+            .filter(|index| self.cells[*index].is_pure())
+            .collect();
+        while let Some(index) = pending.pop() {
+            // already peeled by other key
+            if !self.cells[index].is_pure() {
+                continue;
+            }
+            let key = self.cells[index].key_sum;
+            // key is not stored to this cell by honest table
+            if !self.indexes(&key).contains(&index) {
+                continue;
+            }
+            // each key is peeled once, more peels than cells never decode
+            if !peeled.insert(key) || self.cells.len() < peeled.len() {
+                return Err(format!("failed to decode, key is peeled twice in {} cells", self.cells.len()));
+            }
+            let sign = self.cells[index].count;
+            if sign == 1 {
+                positive.push(key);
+            } else {
+                negative.push(key);
+            }
+            self.toggle(&key, -sign);
+            for index in self.indexes(&key).iter() {
+                if self.cells[*index].is_pure() {
+                    pending.push(*index);
+                }
+            }
+        }
+        if self.cells.iter().all(|cell| cell.is_empty()) {
+            Ok((positive, negative))
+        } else {
+            Err(format!("failed to decode, difference is too large for {} cells", self.cells.len()))
+        }
+    }
+
+    // [cells u32][cells: count i32, key_sum 32b, hash_sum u64]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(4 + self.cells.len() * CELL_SIZE);
+        buf.extend_from_slice(&u32_to_bytes(self.cells.len() as u32));
+        for cell in self.cells.iter() {
+            buf.extend_from_slice(&u32_to_bytes(cell.count as u32));
+            buf.extend_from_slice(&cell.key_sum);
+            buf.extend_from_slice(&cell.hash_sum.to_le_bytes());
+        }
+        buf
+    }
+
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, String> {
+        if buf.len() < 4 {
+            return Err("sketch is too short".to_owned());
+        }
+        let size = bytes_to_u32(&buf[0..4]) as usize;
+        if size == 0 || size % HASH_COUNT != 0 || MAX_CELLS < size {
+            return Err(format!("not correct cells size {}", size));
+        }
+        if buf.len() != 4 + size * CELL_SIZE {
+            return Err(format!("sketch length is not match {}!={}", buf.len(), 4 + size * CELL_SIZE));
+        }
+        let cells = buf[4..].chunks(CELL_SIZE)
+            .map(|chunk| {
+                let mut key_sum = [0u8; 32];
+                key_sum.copy_from_slice(&chunk[4..36]);
+                let mut hash_sum = [0u8; 8];
+                hash_sum.copy_from_slice(&chunk[36..44]);
+                Cell {
+                    count: bytes_to_u32(&chunk[0..4]) as i32,
+                    key_sum,
+                    hash_sum: u64::from_le_bytes(hash_sum),
+                }
+            })
+            .collect();
+        Ok(Iblt {cells})
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(i: u32) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        key[0..4].copy_from_slice(&u32_to_bytes(i));
+        key[31] = 0xff;
+        key
+    }
+
+    #[test]
+    fn decode_symmetric_difference() {
+        let mut mine = Iblt::new(60);
+        let mut theirs = Iblt::new(60);
+        for i in 0..1000 {
+            mine.insert(&key(i));
+        }
+        for i in 10..1015 {
+            theirs.insert(&key(i));
+        }
+        let theirs = Iblt::from_bytes(&theirs.to_bytes()).unwrap();
+        let (mut positive, mut negative) = mine.subtract(&theirs).unwrap().decode().unwrap();
+        let mut expected_positive: Vec<[u8; 32]> = (0..10).map(key).collect();
+        let mut expected_negative: Vec<[u8; 32]> = (1000..1015).map(key).collect();
+        for keys in [&mut positive, &mut negative, &mut expected_positive, &mut expected_negative].iter_mut() {
+            keys.sort();
+        }
+        assert_eq!(positive, expected_positive);
+        assert_eq!(negative, expected_negative);
+
+        // too small table
+        let mut small = Iblt::new(3);
+        for i in 0..100 {
+            small.insert(&key(i));
+        }
+        assert!(small.decode().is_err());
+    }
+
+    #[test]
+    fn decode_crafted_sketch() {
+        // key toggled in only one of own cells, peeling would undo itself forever
+        let mut crafted = Iblt::new(30);
+        let index = crafted.indexes(&key(1))[0];
+        let check = siphash(CHECK_KEY.0, CHECK_KEY.1, &key(1));
+        crafted.cells[index].toggle(&key(1), check, 1);
+        assert!(crafted.decode().is_err());
+
+        // pure cell of key stored in other's cell is not peeled
+        let mut crafted = Iblt::new(30);
+        let own = crafted.indexes(&key(2));
+        let index = (0..30).find(|index| !own.contains(index)).unwrap();
+        crafted.cells[index].toggle(&key(2), siphash(CHECK_KEY.0, CHECK_KEY.1, &key(2)), 1);
+        assert!(crafted.decode().is_err());
+    }
+}
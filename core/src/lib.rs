@@ -0,0 +1,12 @@
+// pure Rust engine of bc4py extension, no python dependency
+// test without python by `cargo test -p bc4py_core`
+extern crate bigint;
+extern crate sha2;
+extern crate ripemd160;
+
+pub mod txpool;
+pub mod iblt;
+pub mod bloom;
+pub mod bech;
+pub mod fee;
+pub mod utils;
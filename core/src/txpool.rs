@@ -0,0 +1,1775 @@
+use crate::fee::FeeEstimator;
+use crate::iblt::Iblt;
+use crate::bloom::BloomFilter;
+use crate::utils::{u32_to_bytes, bytes_to_u32, sha256double};
+use std::collections::{HashMap, HashSet, BTreeSet, VecDeque};
+use std::cmp::PartialEq;
+use std::fmt;
+use std::hash::Hasher;
+use siphasher::sip::SipHasher24;
+use bigint::U256;
+
+
+// replace tx must pay conflicts fee + (this * own size)
+const REPLACE_PRICE_INCREMENT: u64 = 1;
+// compact block short id is lower 6 bytes of siphash
+const SHORT_ID_MASK: u64 = 0xffff_ffff_ffff;
+// dump file format
+const DUMP_MAGIC: &[u8; 4] = b"BCMP";
+const DUMP_VERSION: u32 = 1;
+// entry without depends, inputs and blob
+// hash + 7 u32 (depends count, inputs count, price, time, deadline, size, blob length)
+const DUMP_ENTRY_MIN_SIZE: usize = 32 + 4 * 7;
+
+
+// NO COPY TRAIT!
+pub struct Unconfirmed<T> {
+    // store TX object
+    pub obj: T,
+    // for find priority
+    pub hash: U256,
+    pub depends: Box<[U256]>,
+    // spent outpoints (txhash, index)
+    pub inputs: Box<[(U256, u32)]>,
+    pub price: u32,
+    pub time: u32,
+    pub deadline: u32,
+    pub size: u32,
+    // extra bloom filter keys (ex. addresses)
+    pub keys: Vec<Vec<u8>>,
+}
+
+impl<T> Unconfirmed<T> {
+    pub fn new(obj: T, hash: U256, mut depends: Vec<U256>, price: u32, time: u32, deadline: u32, size: u32,
+               mut inputs: Vec<(U256, u32)>) -> Self {
+        // remove duplicate depends and inputs, tx never depends on itself
+        depends.retain(|depend| depend != &hash);
+        depends.sort_unstable();
+        depends.dedup();
+        inputs.sort_unstable();
+        inputs.dedup();
+        // drop any excess capacity
+        let depends = depends.into_boxed_slice();
+        let inputs = inputs.into_boxed_slice();
+
+        Unconfirmed {obj, hash, depends, inputs, price, time, deadline, size, keys: Vec::new()}
+    }
+
+    /// match by hash, spent outpoints (txhash 32b + index 4b) or registered keys
+    pub fn is_matching(&self, bloom: &BloomFilter) -> bool {
+        let mut slice = [0u8;36];
+        self.hash.to_big_endian(&mut slice[..32]);
+        if bloom.contains(&slice[..32]) {
+            return true;
+        }
+        for (hash, index) in self.inputs.iter() {
+            hash.to_big_endian(&mut slice[..32]);
+            slice[32..].copy_from_slice(&u32_to_bytes(*index));
+            if bloom.contains(&slice) {
+                return true;
+            }
+        }
+        self.keys.iter().any(|key| bloom.contains(key))
+    }
+
+    pub fn fee(&self) -> u64 {
+        self.price as u64 * self.size as u64
+    }
+}
+
+impl<T> PartialEq for Unconfirmed<T> {
+    fn eq(&self, other: &Unconfirmed<T>) -> bool {
+        self.hash == other.hash
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RemoveReason {
+    Confirmed,
+    Conflict,
+    Expired,
+    Evicted,
+    Manual,
+}
+
+impl RemoveReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RemoveReason::Confirmed => "confirmed",
+            RemoveReason::Conflict => "conflict",
+            RemoveReason::Expired => "expired",
+            RemoveReason::Evicted => "evicted",
+            RemoveReason::Manual => "manual",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum PoolError {
+    AlreadyInserted,
+    AlreadyOrphan,
+    NotFound,
+    DependOnConflict,
+    CyclicDepends,
+    ReplaceFeeTooLow {fee: u64, required: u64},
+    TooManyAncestors {count: usize, limit: usize},
+    AncestorSizeTooLarge {size: u64, limit: u64},
+    TooManyDescendants {hash: U256, count: usize, limit: usize},
+    DescendantSizeTooLarge {hash: U256, size: u64, limit: u64},
+}
+
+impl PoolError {
+    /// violation of package limits
+    pub fn is_limit(&self) -> bool {
+        match self {
+            PoolError::TooManyAncestors {..} | PoolError::AncestorSizeTooLarge {..}
+            | PoolError::TooManyDescendants {..} | PoolError::DescendantSizeTooLarge {..} => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PoolError::AlreadyInserted => write!(f, "already inserted tx"),
+            PoolError::AlreadyOrphan => write!(f, "already inserted orphan tx"),
+            PoolError::NotFound => write!(f, "not found hash"),
+            PoolError::DependOnConflict => write!(f, "depend on conflicted tx"),
+            PoolError::CyclicDepends => write!(f, "depend on own descendant tx"),
+            PoolError::ReplaceFeeTooLow {fee, required} =>
+                write!(f, "replace fee is too low {}<={}", fee, required),
+            PoolError::TooManyAncestors {count, limit} =>
+                write!(f, "too many ancestors {}>{}", count, limit),
+            PoolError::AncestorSizeTooLarge {size, limit} =>
+                write!(f, "ancestors size is too large {}>{}", size, limit),
+            PoolError::TooManyDescendants {hash, count, limit} =>
+                write!(f, "too many descendants of {:x} {}>{}", hash, count, limit),
+            PoolError::DescendantSizeTooLarge {hash, size, limit} =>
+                write!(f, "descendants size of {:x} is too large {}>{}", hash, size, limit),
+        }
+    }
+}
+
+// package limits checked at push and batch insert, counts and sizes include the tx itself
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolLimits {
+    pub max_ancestors: usize,
+    pub max_ancestor_size: u64,
+    pub max_descendants: usize,
+    pub max_descendant_size: u64,
+}
+
+impl Default for PoolLimits {
+    fn default() -> Self {
+        PoolLimits {
+            max_ancestors: 25,
+            max_ancestor_size: 101000,
+            max_descendants: 25,
+            max_descendant_size: 101000,
+        }
+    }
+}
+
+impl PoolLimits {
+    pub fn unlimited() -> Self {
+        PoolLimits {
+            max_ancestors: usize::max_value(),
+            max_ancestor_size: u64::max_value(),
+            max_descendants: usize::max_value(),
+            max_descendant_size: u64::max_value(),
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self == &PoolLimits::unlimited()
+    }
+}
+
+// pool mutation result, used for callbacks
+pub struct Changes<T> {
+    pub added: Vec<U256>,
+    pub removed: Vec<(Unconfirmed<T>, RemoveReason)>,
+    pub reordered: Vec<U256>,
+}
+
+impl<T> Default for Changes<T> {
+    fn default() -> Self {
+        Changes {
+            added: Vec::new(),
+            removed: Vec::new(),
+            reordered: Vec::new(),
+        }
+    }
+}
+
+impl<T> Changes<T> {
+    fn remove_all(&mut self, txs: Vec<Unconfirmed<T>>, reason: RemoveReason) {
+        self.removed.extend(txs.into_iter().map(|tx| (tx, reason)));
+    }
+
+    /// removed txs by the reason
+    pub fn into_removed(self, reason: RemoveReason) -> Vec<Unconfirmed<T>> {
+        self.removed.into_iter()
+            .filter(|(_, _reason)| *_reason == reason)
+            .map(|(tx, _)| tx)
+            .collect()
+    }
+}
+
+pub struct PoolStats {
+    pub count: usize,
+    pub total_size: u64,
+    pub total_fee: u64,
+    pub min_price: Option<u32>,
+    pub max_price: Option<u32>,
+    pub median_price: Option<u32>,
+    pub oldest_time: Option<u32>,
+    pub depends_count: usize,
+    // (lower_price, count, size)
+    pub histogram: Vec<(u32, usize, u64)>,
+    // (txhash, price delta) including txs not in pool
+    pub deltas: Vec<(U256, i64)>,
+}
+
+// conditions of query, None is no condition (bounds are inclusive)
+#[derive(Clone, Default, Debug)]
+pub struct Query {
+    pub min_price: Option<u32>,
+    pub max_price: Option<u32>,
+    pub since: Option<u32>,
+    pub until: Option<u32>,
+    pub has_depends: Option<bool>,
+    pub min_size: Option<u32>,
+    pub max_size: Option<u32>,
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    pub fn is_matching<T>(&self, tx: &Unconfirmed<T>) -> bool {
+        self.min_price.map_or(true, |price| price <= tx.price)
+            && self.max_price.map_or(true, |price| tx.price <= price)
+            && self.since.map_or(true, |time| time <= tx.time)
+            && self.until.map_or(true, |time| tx.time <= time)
+            && self.has_depends.map_or(true, |flag| flag == (0 < tx.depends.len()))
+            && self.min_size.map_or(true, |size| size <= tx.size)
+            && self.max_size.map_or(true, |size| tx.size <= size)
+    }
+}
+
+// entry of dump file without TX object
+pub struct DumpEntry {
+    pub hash: U256,
+    pub depends: Vec<U256>,
+    pub inputs: Vec<(U256, u32)>,
+    pub price: u32,
+    pub time: u32,
+    pub deadline: u32,
+    pub size: u32,
+    pub blob: Vec<u8>,
+}
+
+struct DumpReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> DumpReader<'a> {
+    fn read(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.buf.len() - self.pos < len {
+            return Err(format!("unexpected end of dump at {}", self.pos));
+        }
+        let bytes = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, String> {
+        Ok(bytes_to_u32(self.read(4)?))
+    }
+
+    fn read_u256(&mut self) -> Result<U256, String> {
+        Ok(U256::from(self.read(32)?))
+    }
+}
+
+fn write_u256(buf: &mut Vec<u8>, hash: &U256) {
+    let mut slice = [0u8;32];
+    hash.to_big_endian(&mut slice);
+    buf.extend_from_slice(&slice);
+}
+
+// [magic 4b][version u32][count u32][entries..][sha256d checksum 32b]
+pub fn encode_dump(entries: &[DumpEntry]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(DUMP_MAGIC);
+    buf.extend_from_slice(&u32_to_bytes(DUMP_VERSION));
+    buf.extend_from_slice(&u32_to_bytes(entries.len() as u32));
+    for entry in entries.iter() {
+        write_u256(&mut buf, &entry.hash);
+        buf.extend_from_slice(&u32_to_bytes(entry.depends.len() as u32));
+        for hash in entry.depends.iter() {
+            write_u256(&mut buf, hash);
+        }
+        buf.extend_from_slice(&u32_to_bytes(entry.inputs.len() as u32));
+        for (hash, index) in entry.inputs.iter() {
+            write_u256(&mut buf, hash);
+            buf.extend_from_slice(&u32_to_bytes(*index));
+        }
+        buf.extend_from_slice(&u32_to_bytes(entry.price));
+        buf.extend_from_slice(&u32_to_bytes(entry.time));
+        buf.extend_from_slice(&u32_to_bytes(entry.deadline));
+        buf.extend_from_slice(&u32_to_bytes(entry.size));
+        buf.extend_from_slice(&u32_to_bytes(entry.blob.len() as u32));
+        buf.extend_from_slice(entry.blob.as_slice());
+    }
+    let checksum = sha256double(buf.as_slice());
+    buf.extend_from_slice(checksum.as_slice());
+    buf
+}
+
+pub fn decode_dump(buf: &[u8]) -> Result<Vec<DumpEntry>, String> {
+    if buf.len() < DUMP_MAGIC.len() + 4 + 4 + 32 {
+        return Err("dump is too short".to_owned());
+    }
+    let (body, checksum) = buf.split_at(buf.len() - 32);
+    if sha256double(body).as_slice() != checksum {
+        return Err("dump checksum is not match".to_owned());
+    }
+    let mut reader = DumpReader {buf: body, pos: 0};
+    if reader.read(4)? != DUMP_MAGIC {
+        return Err("dump magic is not match".to_owned());
+    }
+    let version = reader.read_u32()?;
+    if version != DUMP_VERSION {
+        return Err(format!("not supported dump version {}", version));
+    }
+    let count = reader.read_u32()?;
+    // count is not trusted, capacity is bounded by remaining bytes
+    let capacity = (count as usize).min((body.len() - reader.pos) / DUMP_ENTRY_MIN_SIZE);
+    let mut entries = Vec::with_capacity(capacity);
+    for _ in 0..count {
+        let hash = reader.read_u256()?;
+        let mut depends = Vec::new();
+        for _ in 0..reader.read_u32()? {
+            depends.push(reader.read_u256()?);
+        }
+        let mut inputs = Vec::new();
+        for _ in 0..reader.read_u32()? {
+            inputs.push((reader.read_u256()?, reader.read_u32()?));
+        }
+        let price = reader.read_u32()?;
+        let time = reader.read_u32()?;
+        let deadline = reader.read_u32()?;
+        let size = reader.read_u32()?;
+        let blob_len = reader.read_u32()? as usize;
+        let blob = reader.read(blob_len)?.to_vec();
+        entries.push(DumpEntry {hash, depends, inputs, price, time, deadline, size, blob});
+    }
+    if reader.pos != body.len() {
+        return Err("dump has extra bytes".to_owned());
+    }
+    Ok(entries)
+}
+
+
+
+// move out items matching f keeping order of both (stable drain_filter)
+fn drain_where<U, F>(items: &mut Vec<U>, mut f: F) -> Vec<U> where F: FnMut(&U) -> bool {
+    let (drained, kept) = items.drain(..).partition(|item| f(item));
+    *items = kept;
+    drained
+}
+
+
+/// 6 bytes short id of txhash keyed by (k0, k1) for compact block
+pub fn short_id(k0: u64, k1: u64, hash: &U256) -> u64 {
+    let mut slice = [0u8;32];
+    hash.to_big_endian(&mut slice);
+    let mut hasher = SipHasher24::new_with_keys(k0, k1);
+    hasher.write(&slice);
+    hasher.finish() & SHORT_ID_MASK
+}
+
+/// short id -> txhash map of pending txs at the time built
+/// collided ids are kept as None (ambiguous)
+pub struct ShortIdIndex {
+    pub k0: u64,
+    pub k1: u64,
+    ids: HashMap<u64, Option<U256>>,
+}
+
+impl ShortIdIndex {
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn get(&self, short_id: u64) -> Option<&U256> {
+        match self.ids.get(&short_id) {
+            Some(Some(hash)) => Some(hash),
+            _ => None,
+        }
+    }
+}
+
+/// ordering engine generic over tx payload
+/// txs are ordered parents first, then high price and old time first
+pub struct TxPool<T> {
+    // pre-ordered high to low
+    unconfirmed: Vec<Unconfirmed<T>>,
+    // txhash -> position in unconfirmed
+    positions: HashMap<U256, usize>,
+    // depend txhash -> unconfirmed txhashs depend on it (parent may not be in pool)
+    children: HashMap<U256, Vec<U256>>,
+    // spent outpoint (txhash, index) -> spender txhash
+    spent: HashMap<(U256, u32), U256>,
+    // (time, txhash) ordered old to new, for expire
+    by_time: BTreeSet<(u32, U256)>,
+    // (deadline, txhash) ordered old to new, for clear_by_deadline
+    by_deadline: BTreeSet<(u32, U256)>,
+    // txhash -> price delta by prioritise, kept after removed
+    deltas: HashMap<U256, i64>,
+    // txs waiting for unknown depends, ordered by arrival
+    orphans: Vec<Unconfirmed<T>>,
+    // max orphan txs count (0 is disabled)
+    orphan_limit: usize,
+    // ancestor/descendant limits
+    limits: PoolLimits,
+    // incremented by any change of unconfirmed txs or their order
+    version: u64,
+    // fed by accepted txs and connected blocks
+    estimator: FeeEstimator,
+}
+
+impl<T> TxPool<T> {
+    pub fn new(orphan_limit: usize) -> Self {
+        TxPool::with_limits(orphan_limit, PoolLimits::default())
+    }
+
+    pub fn with_limits(orphan_limit: usize, limits: PoolLimits) -> Self {
+        TxPool {
+            unconfirmed: Vec::new(),
+            positions: HashMap::new(),
+            children: HashMap::new(),
+            spent: HashMap::new(),
+            by_time: BTreeSet::new(),
+            by_deadline: BTreeSet::new(),
+            deltas: HashMap::new(),
+            orphans: Vec::new(),
+            orphan_limit,
+            limits,
+            version: 0,
+            estimator: FeeEstimator::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.unconfirmed.len()
+    }
+
+    /// changed when unconfirmed txs or their order change
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// unconfirmed tx at the priority position
+    pub fn at(&self, index: usize) -> Option<&Unconfirmed<T>> {
+        self.unconfirmed.get(index)
+    }
+
+    /// unconfirmed txs ordered by priority
+    pub fn iter(&self) -> std::slice::Iter<'_, Unconfirmed<T>> {
+        self.unconfirmed.iter()
+    }
+
+    /// orphan txs ordered by arrival
+    pub fn orphans(&self) -> &[Unconfirmed<T>] {
+        self.orphans.as_slice()
+    }
+
+    pub fn get(&self, hash: &U256) -> Option<&Unconfirmed<T>> {
+        self.positions.get(hash).and_then(|index| self.unconfirmed.get(*index))
+    }
+
+    pub fn position(&self, hash: &U256) -> Option<usize> {
+        self.positions.get(hash).cloned()
+    }
+
+    pub fn spender_of(&self, outpoint: &(U256, u32)) -> Option<&U256> {
+        self.spent.get(outpoint)
+    }
+
+    /// price used for ordering, real price with prioritise delta
+    pub fn effective_price(&self, tx: &Unconfirmed<T>) -> u32 {
+        match self.deltas.get(&tx.hash) {
+            Some(delta) => (tx.price as i64).saturating_add(*delta).max(0).min(u32::max_value() as i64) as u32,
+            None => tx.price,
+        }
+    }
+
+    /// accumulated price delta of the tx
+    pub fn get_delta(&self, hash: &U256) -> i64 {
+        self.deltas.get(hash).cloned().unwrap_or(0)
+    }
+
+    /// find spender txs of outpoints without duplicate
+    pub fn find_conflicts(&self, outpoints: &[(U256, u32)]) -> Vec<U256> {
+        let mut conflicts = Vec::new();
+        for outpoint in outpoints.iter() {
+            if let Some(hash) = self.spent.get(outpoint) {
+                if !conflicts.contains(hash) {
+                    conflicts.push(hash.clone());
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// push new tx with replace conflicts and promote orphans
+    /// return inserted index (None if held as orphan)
+    /// depends must list only unconfirmed parents, caller drops parents already in blocks
+    /// (or calls mark_confirmed later), otherwise the tx waits as orphan forever
+    pub fn push(&mut self, unconfirmed: Unconfirmed<T>) -> Result<(Option<usize>, Changes<T>), PoolError> {
+        let mut changes = Changes::default();
+
+        // check before remove conflicts
+        if self.position(&unconfirmed.hash).is_some() {
+            return Err(PoolError::AlreadyInserted);
+        }
+
+        // hold orphan until depends arrive
+        if 0 < self.orphan_limit && !self.is_known_depends(&unconfirmed) {
+            let evicted = self.push_orphan(unconfirmed)?;
+            changes.remove_all(evicted, RemoveReason::Evicted);
+            return Ok((None, changes));
+        }
+        self.check_cyclic(&unconfirmed)?;
+
+        // conflicts are counted, removing them only loosen limits
+        self.check_limits(&unconfirmed)?;
+
+        // replace conflicts
+        let replaced = self.replace_conflicts(&unconfirmed)?;
+        changes.remove_all(replaced, RemoveReason::Conflict);
+
+        // push, same tx held as orphan is inserted now
+        let hash = unconfirmed.hash.clone();
+        self.orphans.retain(|tx| tx.hash != hash);
+        self.estimator.process_entry(&hash, unconfirmed.price);
+        let index = self.push_unconfirmed(unconfirmed);
+        changes.added.push(hash.clone());
+        self.promote_orphans(&mut changes);
+        let index = self.position(&hash).unwrap_or(index);
+        self.track_removed(&changes);
+        Ok((Some(index), changes))
+    }
+
+    /// add price delta to the tx and reorder with its dependency chain
+    /// delta is accumulated (saturated at i64 bounds) and applied again when the tx is re-added
+    /// fee is not changed, only affect ordering
+    pub fn prioritise(&mut self, hash: &U256, price_delta: i64) -> (i64, Changes<T>) {
+        let delta = self.get_delta(hash).saturating_add(price_delta);
+        if delta == 0 {
+            self.deltas.remove(hash);
+        } else {
+            self.deltas.insert(hash.clone(), delta);
+        }
+        // re-insert the tx with its depends by new priority
+        let mut changes = Changes::default();
+        let moved = self.take_with_descendants(&[hash.clone()]);
+        changes.reordered = moved.iter().map(|tx| tx.hash.clone()).collect();
+        self.insert_all(moved);
+        (delta, changes)
+    }
+
+    /// remove the tx and re-insert txs depend on it
+    pub fn remove(&mut self, hash: &U256) -> Result<Changes<T>, PoolError> {
+        // remove all related txs, root tx is first
+        let mut deleted = self.take_with_descendants(&[hash.clone()]);
+        if deleted.len() == 0 {
+            return Err(PoolError::NotFound);
+        }
+        let root = deleted.remove(0);
+        // insert all
+        let mut changes = Changes::default();
+        changes.reordered = deleted.iter().map(|tx| tx.hash.clone()).collect();
+        self.insert_all(deleted);
+        changes.removed.push((root, RemoveReason::Manual));
+        self.track_removed(&changes);
+        Ok(changes)
+    }
+
+    /// remove txs and re-insert txs depend on them (no error even if no delete tx)
+    pub fn remove_many(&mut self, hashs: &[U256]) -> Changes<T> {
+        // remove all related txs
+        let mut deleted = self.take_with_descendants(hashs);
+        // remove root txs
+        let roots: Vec<Unconfirmed<T>> = drain_where(&mut deleted, |_tx| hashs.contains(&_tx.hash));
+        // insert all
+        let mut changes = Changes::default();
+        changes.reordered = deleted.iter().map(|tx| tx.hash.clone()).collect();
+        self.insert_all(deleted);
+        changes.remove_all(roots, RemoveReason::Manual);
+        self.track_removed(&changes);
+        changes
+    }
+
+    /// remove the tx with txs depend on it
+    pub fn remove_with_depends(&mut self, hash: &U256) -> Result<Changes<T>, PoolError> {
+        let deleted = self.take_with_descendants(&[hash.clone()]);
+        if deleted.len() == 0 {
+            return Err(PoolError::NotFound);
+        }
+        let mut changes = Changes::default();
+        changes.remove_all(deleted, RemoveReason::Manual);
+        self.track_removed(&changes);
+        Ok(changes)
+    }
+
+    /// remove txs included in new block and txs conflict with block's spent outpoints
+    pub fn block_connected(&mut self, hashs: &[U256], outpoints: &[(U256, u32)]) -> Changes<T> {
+        let mut changes = Changes::default();
+
+        // remove included txs and re-insert others
+        let mut deleted = self.take_with_descendants(hashs);
+        let included: Vec<Unconfirmed<T>> = drain_where(&mut deleted, |_tx| hashs.contains(&_tx.hash));
+        changes.remove_all(included, RemoveReason::Confirmed);
+        changes.reordered = deleted.iter().map(|tx| tx.hash.clone()).collect();
+        self.insert_all(deleted);
+
+        // remove conflicts with depends
+        let conflicts = self.find_conflicts(outpoints);
+        let conflicts = self.take_with_descendants(&conflicts);
+        changes.remove_all(conflicts, RemoveReason::Conflict);
+
+        // confirmed depends never arrive to orphans
+        let promoted = self.mark_confirmed(hashs);
+        changes.removed.extend(promoted.removed);
+        changes.added.extend(promoted.added);
+
+        // confirmed txs never re-added
+        for hash in hashs.iter() {
+            self.deltas.remove(hash);
+        }
+
+        // feed included txs to fee estimator
+        self.track_removed(&changes);
+        self.estimator.process_block(hashs);
+        changes
+    }
+
+    /// tell parents of orphans are confirmed by block, orphans waiting only them are promoted
+    /// orphans of the confirmed hash itself are removed as confirmed
+    pub fn mark_confirmed(&mut self, hashs: &[U256]) -> Changes<T> {
+        let mut changes = Changes::default();
+        let included: Vec<Unconfirmed<T>> = drain_where(&mut self.orphans, |tx| hashs.contains(&tx.hash));
+        changes.remove_all(included, RemoveReason::Confirmed);
+        for tx in self.orphans.iter_mut() {
+            if tx.depends.iter().any(|hash| hashs.contains(hash)) {
+                tx.depends = tx.depends.iter()
+                    .filter(|hash| !hashs.contains(hash))
+                    .cloned()
+                    .collect::<Vec<U256>>()
+                    .into_boxed_slice();
+            }
+        }
+        self.promote_orphans(&mut changes);
+        self.track_removed(&changes);
+        changes
+    }
+
+    /// insert many txs at once (ex. txs of disconnected block)
+    /// return txs no longer fit (conflict with pool, cyclic depends, over limits or depend on such tx)
+    pub fn insert_batch(&mut self, txs: Vec<Unconfirmed<T>>) -> (Vec<Unconfirmed<T>>, Changes<T>) {
+        let mut accepted: Vec<Unconfirmed<T>> = Vec::with_capacity(txs.len());
+        let mut rejected: Vec<Unconfirmed<T>> = Vec::new();
+        for unconfirmed in txs {
+            // already inserted
+            if self.position(&unconfirmed.hash).is_some()
+                || accepted.iter().any(|tx| tx.hash == unconfirmed.hash) {
+                continue;
+            }
+            // conflict with pool or other inserting txs
+            let is_conflict = 0 < self.find_conflicts(&unconfirmed.inputs).len()
+                || accepted.iter().any(|tx| tx.inputs.iter().any(|input| unconfirmed.inputs.contains(input)));
+            if is_conflict {
+                rejected.push(unconfirmed);
+            } else {
+                accepted.push(unconfirmed);
+            }
+        }
+
+        // txs may be listed child first, insert parents first
+        let (accepted, cyclic) = sort_parents_first(accepted);
+        rejected.extend(cyclic);
+        let mut changes = Changes::default();
+        for unconfirmed in accepted {
+            let is_rejected_depend = rejected.iter()
+                .any(|tx| unconfirmed.depends.contains(&tx.hash));
+            if is_rejected_depend
+                || self.check_cyclic(&unconfirmed).is_err()
+                || self.check_limits(&unconfirmed).is_err() {
+                rejected.push(unconfirmed);
+                continue;
+            }
+            changes.added.push(unconfirmed.hash.clone());
+            self.push_unconfirmed(unconfirmed);
+        }
+        self.promote_orphans(&mut changes);
+        self.track_removed(&changes);
+        (rejected, changes)
+    }
+
+    /// remove all unconfirmed txs and orphans
+    pub fn clear_all(&mut self) -> Changes<T> {
+        let mut deleted: Vec<Unconfirmed<T>> = self.unconfirmed.drain(..).collect();
+        deleted.extend(self.orphans.drain(..));
+        self.positions.clear();
+        self.children.clear();
+        self.spent.clear();
+        self.version = self.version.wrapping_add(1);
+        self.by_time.clear();
+        self.by_deadline.clear();
+        let mut changes = Changes::default();
+        changes.remove_all(deleted, RemoveReason::Manual);
+        self.track_removed(&changes);
+        changes
+    }
+
+    /// remove expired unconfirmed txs and orphans
+    /// expired txs are found by deadline index in O(k log n), but removal is O(n) not O(k log n)
+    /// pool order is a Vec, so the tail after the first removed tx is shifted and re-indexed
+    /// nothing expired returns in O(log n) without touching the pool
+    pub fn clear_by_deadline(&mut self, deadline: u32) -> Changes<T> {
+        // remove too old tx with depends
+        let expired: Vec<U256> = self.by_deadline.iter()
+            .take_while(|(_deadline, _)| *_deadline < deadline)
+            .map(|(_, hash)| hash.clone())
+            .collect();
+        let mut deleted = self.take_with_descendants(&expired);
+        // remove too old orphans
+        deleted.extend(drain_where(&mut self.orphans, |tx| tx.deadline < deadline));
+        let mut changes = Changes::default();
+        changes.remove_all(deleted, RemoveReason::Expired);
+        self.track_removed(&changes);
+        changes
+    }
+
+    /// remove txs older than max_age from now (by entry time) with depends and orphans
+    /// cost is same as clear_by_deadline, found in O(k log n) by time index but removed in O(n)
+    pub fn expire(&mut self, now: u32, max_age: u32) -> Changes<T> {
+        let threshold = now.saturating_sub(max_age);
+        let expired: Vec<U256> = self.by_time.iter()
+            .take_while(|(time, _)| *time < threshold)
+            .map(|(_, hash)| hash.clone())
+            .collect();
+        let mut deleted = self.take_with_descendants(&expired);
+        deleted.extend(drain_where(&mut self.orphans, |tx| tx.time < threshold));
+        let mut changes = Changes::default();
+        changes.remove_all(deleted, RemoveReason::Expired);
+        self.track_removed(&changes);
+        changes
+    }
+
+    /// statistics with price histogram by buckets lower bounds
+    pub fn stats(&self, mut buckets: Vec<u32>) -> PoolStats {
+        buckets.push(0);
+        buckets.sort_unstable();
+        buckets.dedup();
+
+        let mut prices: Vec<u32> = self.unconfirmed.iter().map(|tx| tx.price).collect();
+        prices.sort_unstable();
+        let median_price = match prices.len() {
+            0 => None,
+            len if len % 2 == 0 => Some(((prices[len/2 - 1] as u64 + prices[len/2] as u64) / 2) as u32),
+            len => Some(prices[len/2]),
+        };
+
+        let mut histogram: Vec<(u32, usize, u64)> = buckets.iter()
+            .map(|lower| (*lower, 0, 0))
+            .collect();
+        for tx in self.unconfirmed.iter() {
+            // buckets[0] is 0, so always found
+            let index = buckets.iter().rposition(|lower| *lower <= tx.price).unwrap_or(0);
+            histogram[index].1 += 1;
+            histogram[index].2 += tx.size as u64;
+        }
+
+        PoolStats {
+            count: self.unconfirmed.len(),
+            total_size: self.unconfirmed.iter().map(|tx| tx.size as u64).sum(),
+            total_fee: self.unconfirmed.iter().map(|tx| tx.fee()).sum(),
+            min_price: prices.first().cloned(),
+            max_price: prices.last().cloned(),
+            median_price,
+            oldest_time: self.unconfirmed.iter().map(|tx| tx.time).min(),
+            depends_count: self.unconfirmed.iter().filter(|tx| 0 < tx.depends.len()).count(),
+            histogram,
+            deltas: self.deltas.iter()
+                .map(|(hash, delta)| (hash.clone(), *delta))
+                .collect(),
+        }
+    }
+
+    /// estimate price to be confirmed within target blocks
+    pub fn estimate_fee(&self, target_blocks: u32) -> Option<u32> {
+        self.estimator.estimate(target_blocks)
+    }
+
+    /// build short id index of unconfirmed txs for compact block
+    pub fn short_id_index(&self, k0: u64, k1: u64) -> ShortIdIndex {
+        let mut ids: HashMap<u64, Option<U256>> = HashMap::with_capacity(self.unconfirmed.len());
+        for tx in self.unconfirmed.iter() {
+            let id = short_id(k0, k1, &tx.hash);
+            if ids.contains_key(&id) {
+                ids.insert(id, None);
+            } else {
+                ids.insert(id, Some(tx.hash.clone()));
+            }
+        }
+        ShortIdIndex {k0, k1, ids}
+    }
+
+    /// find txs of short ids, None if unknown, collided or already removed
+    /// ids listed twice in short_ids are None too (same tx never appear twice in a block)
+    pub fn resolve_short_ids(&self, index: &ShortIdIndex, short_ids: &[u64]) -> Vec<Option<&Unconfirmed<T>>> {
+        let mut counts: HashMap<u64, usize> = HashMap::with_capacity(short_ids.len());
+        for short_id in short_ids.iter() {
+            *counts.entry(*short_id).or_insert(0) += 1;
+        }
+        short_ids.iter()
+            .map(|short_id| match counts.get(short_id) {
+                Some(1) => index.get(*short_id).and_then(|hash| self.get(hash)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// fill block positions (short ids + prefilled) by short ids in order
+    /// prefilled is absolute positions in block, they are left None and not missing
+    /// return (txs of all positions, missing positions) or error by wrong prefilled position
+    pub fn reconstruct(&self, index: &ShortIdIndex, short_ids: &[u64], prefilled: &[usize])
+        -> Result<(Vec<Option<&Unconfirmed<T>>>, Vec<usize>), String> {
+        let total = short_ids.len() + prefilled.len();
+        let mut is_prefilled = vec![false; total];
+        for position in prefilled.iter() {
+            if total <= *position || is_prefilled[*position] {
+                return Err(format!("wrong prefilled index {}", position));
+            }
+            is_prefilled[*position] = true;
+        }
+        let mut txs: Vec<Option<&Unconfirmed<T>>> = vec![None; total];
+        let mut missing = Vec::new();
+        let found = self.resolve_short_ids(index, short_ids);
+        let positions = (0..total).filter(|position| !is_prefilled[*position]);
+        for (position, tx) in positions.zip(found.into_iter()) {
+            match tx {
+                Some(tx) => txs[position] = Some(tx),
+                None => missing.push(position),
+            }
+        }
+        Ok((txs, missing))
+    }
+
+    /// add bloom filter keys to unconfirmed tx or orphan
+    pub fn register_keys(&mut self, hash: &U256, keys: Vec<Vec<u8>>) -> Result<(), PoolError> {
+        let tx = self.unconfirmed.iter_mut()
+            .chain(self.orphans.iter_mut())
+            .find(|tx| hash == &tx.hash);
+        match tx {
+            Some(tx) => {
+                tx.keys.extend(keys);
+                Ok(())
+            },
+            None => Err(PoolError::NotFound),
+        }
+    }
+
+    /// unconfirmed txs matching the query ordered by priority
+    pub fn query(&self, query: &Query) -> Vec<&Unconfirmed<T>> {
+        self.unconfirmed.iter()
+            .filter(|tx| query.is_matching(tx))
+            .take(query.limit.unwrap_or(usize::max_value()))
+            .collect()
+    }
+
+    /// unconfirmed txs matching the bloom filter ordered by priority
+    pub fn filter_matching(&self, bloom: &BloomFilter) -> Vec<&Unconfirmed<T>> {
+        self.unconfirmed.iter()
+            .filter(|tx| tx.is_matching(bloom))
+            .collect()
+    }
+
+    /// sketch of unconfirmed txs for set reconciliation with peer
+    pub fn sketch(&self, cells: usize) -> Iblt {
+        let mut sketch = Iblt::new(cells);
+        let mut slice = [0u8;32];
+        for tx in self.unconfirmed.iter() {
+            tx.hash.to_big_endian(&mut slice);
+            sketch.insert(&slice);
+        }
+        sketch
+    }
+
+    /// symmetric difference with peer's sketch of same cells size
+    /// return (only in my pool, only in peer's pool)
+    pub fn reconcile(&self, peer: &Iblt) -> Result<(Vec<U256>, Vec<U256>), String> {
+        let (mine, theirs) = self.sketch(peer.len()).subtract(peer)?.decode()?;
+        let mine = mine.iter().map(|hash| U256::from(&hash[..])).collect();
+        let theirs = theirs.iter().map(|hash| U256::from(&hash[..])).collect();
+        Ok((mine, theirs))
+    }
+
+    /// dump entries ordered by priority (blob is empty)
+    pub fn dump_entries(&self) -> Vec<DumpEntry> {
+        self.unconfirmed.iter()
+            .map(|tx| DumpEntry {
+                hash: tx.hash.clone(),
+                depends: tx.depends.to_vec(),
+                inputs: tx.inputs.to_vec(),
+                price: tx.price,
+                time: tx.time,
+                deadline: tx.deadline,
+                size: tx.size,
+                blob: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// verify ordering and index invariants, return first broken one
+    pub fn check_consistency(&self) -> Result<(), String> {
+        // no duplicate hashs
+        let mut positions: HashMap<&U256, usize> = HashMap::with_capacity(self.unconfirmed.len());
+        for (index, tx) in self.unconfirmed.iter().enumerate() {
+            if positions.insert(&tx.hash, index).is_some() {
+                return Err(format!("duplicate tx {:x} at {}", tx.hash, index));
+            }
+        }
+        for (index, tx) in self.orphans.iter().enumerate() {
+            if positions.contains_key(&tx.hash)
+                || self.orphans[..index].iter().any(|_tx| _tx.hash == tx.hash) {
+                return Err(format!("duplicate orphan {:x}", tx.hash));
+            }
+        }
+
+        // parents appear before children
+        for (index, tx) in self.unconfirmed.iter().enumerate() {
+            for depend in tx.depends.iter() {
+                if let Some(parent) = positions.get(depend) {
+                    if index <= *parent {
+                        return Err(format!("tx {:x} at {} is before parent at {}", tx.hash, index, parent));
+                    }
+                }
+            }
+        }
+
+        // non-dependent neighbours are ordered by price and time
+        for (index, pair) in self.unconfirmed.windows(2).enumerate() {
+            let (high, low) = (&pair[0], &pair[1]);
+            if low.depends.contains(&high.hash) {
+                continue;
+            }
+            let (high_price, low_price) = (self.effective_price(high), self.effective_price(low));
+            if high_price < low_price || (high_price == low_price && high.time > low.time) {
+                return Err(format!("tx at {} is lower priority than next", index));
+            }
+        }
+
+        // spent index matches inputs
+        let mut inputs_count = 0;
+        for tx in self.unconfirmed.iter() {
+            for input in tx.inputs.iter() {
+                if self.spent.get(input) != Some(&tx.hash) {
+                    return Err(format!("input of tx {:x} is not indexed", tx.hash));
+                }
+            }
+            inputs_count += tx.inputs.len();
+        }
+        if self.spent.len() != inputs_count {
+            return Err(format!("spent index has {} extra outpoints", self.spent.len() as isize - inputs_count as isize));
+        }
+
+        // time index matches txs
+        if self.by_time.len() != self.unconfirmed.len()
+            || self.unconfirmed.iter().any(|tx| !self.by_time.contains(&(tx.time, tx.hash.clone()))) {
+            return Err("time index is not match".to_owned());
+        }
+
+        // deadline index matches txs
+        if self.by_deadline.len() != self.unconfirmed.len()
+            || self.unconfirmed.iter().any(|tx| !self.by_deadline.contains(&(tx.deadline, tx.hash.clone()))) {
+            return Err("deadline index is not match".to_owned());
+        }
+
+        // position index matches order
+        if self.positions.len() != positions.len()
+            || positions.iter().any(|(hash, index)| self.positions.get(*hash) != Some(index)) {
+            return Err("position index is not match".to_owned());
+        }
+
+        // children index matches depends
+        let mut children_count = 0;
+        for tx in self.unconfirmed.iter() {
+            for depend in tx.depends.iter() {
+                if !self.children.get(depend).map(|children| children.contains(&tx.hash)).unwrap_or(false) {
+                    return Err(format!("tx {:x} is not indexed as child", tx.hash));
+                }
+            }
+            children_count += tx.depends.len();
+        }
+        if self.children.values().map(|children| children.len()).sum::<usize>() != children_count {
+            return Err("children index has extra txs".to_owned());
+        }
+        Ok(())
+    }
+}
+
+// row level methods only used inner
+impl<T> TxPool<T> {
+    // feed txs removed without confirm to fee estimator
+    // confirmed txs are fed by process_block, manual removal is no data
+    fn track_removed(&mut self, changes: &Changes<T>) {
+        for (tx, reason) in changes.removed.iter() {
+            match reason {
+                RemoveReason::Confirmed => (),
+                RemoveReason::Manual => self.estimator.forget(&tx.hash),
+                _ => self.estimator.process_removal(&tx.hash),
+            }
+        }
+    }
+
+    // all depends are in unconfirmed
+    fn is_known_depends(&self, unconfirmed: &Unconfirmed<T>) -> bool {
+        unconfirmed.depends.iter()
+            .all(|hash| self.positions.contains_key(hash))
+    }
+
+    // hold tx until depends arrive, drop oldest orphan when over limit
+    // return evicted orphans
+    fn push_orphan(&mut self, unconfirmed: Unconfirmed<T>) -> Result<Vec<Unconfirmed<T>>, PoolError> {
+        if self.orphans.iter().any(|tx| tx.hash == unconfirmed.hash) {
+            return Err(PoolError::AlreadyOrphan);
+        }
+        self.orphans.push(unconfirmed);
+        let mut evicted = Vec::new();
+        while self.orphan_limit < self.orphans.len() {
+            evicted.push(self.orphans.remove(0));
+        }
+        Ok(evicted)
+    }
+
+    // push orphans which depends are all known now
+    // promoted hashs and replaced conflicts are recorded to changes
+    // orphans over the limits are evicted, lost replacement is conflict
+    fn promote_orphans(&mut self, changes: &mut Changes<T>) {
+        loop {
+            let index = self.orphans.iter()
+                .position(|tx| self.is_known_depends(tx));
+            let unconfirmed = match index {
+                Some(index) => self.orphans.remove(index),
+                None => break,
+            };
+            // inserted by other way (ex. insert_batch), nothing lost
+            if self.position(&unconfirmed.hash).is_some() {
+                continue;
+            }
+            if self.check_cyclic(&unconfirmed).is_err() || self.check_limits(&unconfirmed).is_err() {
+                changes.removed.push((unconfirmed, RemoveReason::Evicted));
+                continue;
+            }
+            match self.replace_conflicts(&unconfirmed) {
+                Ok(deleted) => changes.remove_all(deleted, RemoveReason::Conflict),
+                Err(_) => {
+                    changes.removed.push((unconfirmed, RemoveReason::Conflict));
+                    continue;
+                },
+            }
+            self.estimator.process_entry(&unconfirmed.hash, unconfirmed.price);
+            changes.added.push(unconfirmed.hash.clone());
+            self.push_unconfirmed(unconfirmed);
+        }
+    }
+
+    // collect hashs with all txs depend on them by children index
+    fn collect_depend_myself(&self, hashs: &[U256]) -> HashSet<U256> {
+        let mut collected = HashSet::new();
+        let mut stack = hashs.to_vec();
+        while let Some(hash) = stack.pop() {
+            if let Some(children) = self.children.get(&hash) {
+                if !collected.contains(&hash) {
+                    stack.extend(children.iter().cloned());
+                }
+            }
+            collected.insert(hash);
+        }
+        collected
+    }
+
+    // collect in pool ancestors of depends
+    fn collect_ancestors(&self, depends: &[U256]) -> Vec<&Unconfirmed<T>> {
+        let mut collected: Vec<&Unconfirmed<T>> = Vec::new();
+        let mut visited = HashSet::new();
+        let mut stack = depends.to_vec();
+        while let Some(hash) = stack.pop() {
+            if !visited.insert(hash.clone()) {
+                continue;
+            }
+            if let Some(tx) = self.get(&hash) {
+                stack.extend(tx.depends.iter().cloned());
+                collected.push(tx);
+            }
+        }
+        collected
+    }
+
+    // check ancestor/descendant limits when the tx is inserted
+    fn check_limits(&self, unconfirmed: &Unconfirmed<T>) -> Result<(), PoolError> {
+        let limits = &self.limits;
+        if limits.is_unlimited() {
+            // nothing to check, skip collecting ancestors of long chain
+            return Ok(());
+        }
+        let size_of = |hashs: &HashSet<U256>| -> u64 {
+            hashs.iter()
+                .filter_map(|hash| self.get(hash))
+                .map(|tx| tx.size as u64)
+                .sum::<u64>() + unconfirmed.size as u64
+        };
+
+        // ancestors of the tx
+        let ancestors = self.collect_ancestors(&unconfirmed.depends);
+        let count = ancestors.len() + 1;
+        if limits.max_ancestors < count {
+            return Err(PoolError::TooManyAncestors {count, limit: limits.max_ancestors});
+        }
+        let size = ancestors.iter().map(|tx| tx.size as u64).sum::<u64>() + unconfirmed.size as u64;
+        if limits.max_ancestor_size < size {
+            return Err(PoolError::AncestorSizeTooLarge {size, limit: limits.max_ancestor_size});
+        }
+
+        // descendants already in pool (arrived before the parent)
+        let descendants = self.collect_depend_myself(&[unconfirmed.hash.clone()]);
+        for tx in descendants.iter().filter_map(|hash| self.get(hash)) {
+            let mut chain: HashSet<U256> = self.collect_ancestors(&tx.depends).iter()
+                .map(|tx| tx.hash.clone())
+                .collect();
+            chain.extend(ancestors.iter().map(|tx| tx.hash.clone()));
+            chain.insert(unconfirmed.hash.clone());
+            let count = chain.len() + 1;
+            if limits.max_ancestors < count {
+                return Err(PoolError::TooManyAncestors {count, limit: limits.max_ancestors});
+            }
+        }
+
+        // descendants of the tx and of each ancestor
+        let mut roots = vec![&unconfirmed.hash];
+        roots.extend(ancestors.iter().map(|tx| &tx.hash));
+        for root in roots {
+            let mut collected = descendants.clone();
+            collected.extend(self.collect_depend_myself(&[root.clone()]));
+            let count = collected.len();
+            if limits.max_descendants < count {
+                return Err(PoolError::TooManyDescendants {hash: root.clone(), count, limit: limits.max_descendants});
+            }
+            let size = size_of(&collected);
+            if limits.max_descendant_size < size {
+                return Err(PoolError::DescendantSizeTooLarge {hash: root.clone(), size, limit: limits.max_descendant_size});
+            }
+        }
+        Ok(())
+    }
+
+    // remove txs which spend same inputs with unconfirmed
+    // return removed txs, or error when replace fee is not enough
+    fn replace_conflicts(&mut self, unconfirmed: &Unconfirmed<T>) -> Result<Vec<Unconfirmed<T>>, PoolError> {
+        let conflicts = self.find_conflicts(&unconfirmed.inputs);
+        if conflicts.len() == 0 {
+            return Ok(Vec::new());
+        }
+
+        // conflicts with depends
+        let collected = self.collect_depend_myself(&conflicts);
+        if unconfirmed.depends.iter().any(|hash| collected.contains(hash)) {
+            return Err(PoolError::DependOnConflict);
+        }
+
+        // replace only when pay strictly higher fee
+        let conflict_fee: u64 = collected.iter()
+            .filter_map(|hash| self.get(hash))
+            .map(|tx| tx.fee())
+            .sum();
+        let required_fee = conflict_fee + REPLACE_PRICE_INCREMENT * unconfirmed.size as u64;
+        if unconfirmed.fee() <= required_fee {
+            return Err(PoolError::ReplaceFeeTooLow {fee: unconfirmed.fee(), required: required_fee});
+        }
+
+        // remove conflicts
+        Ok(self.take_with_descendants(&conflicts))
+    }
+
+    // remove txs with all txs depend on them in one pass
+    // removed txs keep pool order, so parents come first
+    fn take_with_descendants(&mut self, hashs: &[U256]) -> Vec<Unconfirmed<T>> {
+        let roots: Vec<U256> = hashs.iter()
+            .filter(|hash| self.positions.contains_key(hash))
+            .cloned()
+            .collect();
+        if roots.len() == 0 {
+            return Vec::new();
+        }
+        let collected = self.collect_depend_myself(&roots);
+        let start = collected.iter()
+            .filter_map(|hash| self.positions.get(hash))
+            .min()
+            .cloned()
+            .unwrap_or(0);
+        let mut tail = self.unconfirmed.split_off(start);
+        let deleted = drain_where(&mut tail, |tx| collected.contains(&tx.hash));
+        self.unconfirmed.append(&mut tail);
+        for tx in deleted.iter() {
+            self.unregister_indexes(tx);
+        }
+        self.update_positions(start);
+        deleted
+    }
+
+    // re-insert txs taken by take_with_descendants in the order
+    fn insert_all(&mut self, txs: Vec<Unconfirmed<T>>) {
+        for tx in txs {
+            self.insert_ordered(tx);
+        }
+    }
+
+    // insert tx which no pool tx depends on
+    // position is after its parents and before first lower priority tx (price with delta, then old time),
+    // this is same as picking best tx whose parents are picked one by one,
+    // so non-dependent neighbours keep ordered and removing a tx with its descendants keeps this order
+    fn insert_ordered(&mut self, unconfirmed: Unconfirmed<T>) -> usize {
+        let start = unconfirmed.depends.iter()
+            .filter_map(|hash| self.positions.get(hash))
+            .max()
+            .map(|index| index + 1)
+            .unwrap_or(0);
+        let price = self.effective_price(&unconfirmed);
+        let index = self.unconfirmed[start..].iter()
+            .position(|tx| {
+                let tx_price = self.effective_price(tx);
+                tx_price < price || (tx_price == price && unconfirmed.time < tx.time)
+            })
+            .map(|index| start + index)
+            .unwrap_or(self.unconfirmed.len());
+        self.register_indexes(&unconfirmed);
+        self.unconfirmed.insert(index, unconfirmed);
+        self.update_positions(index);
+        index
+    }
+
+    // fix position index of txs after start (moved by insert or remove)
+    fn update_positions(&mut self, start: usize) {
+        for (index, tx) in self.unconfirmed.iter().enumerate().skip(start) {
+            self.positions.insert(tx.hash.clone(), index);
+        }
+    }
+
+    // add tx to children, spent, time and deadline index (position is updated by caller)
+    fn register_indexes(&mut self, unconfirmed: &Unconfirmed<T>) {
+        self.version = self.version.wrapping_add(1);
+        for depend in unconfirmed.depends.iter() {
+            self.children.entry(depend.clone()).or_insert_with(Vec::new).push(unconfirmed.hash.clone());
+        }
+        for input in unconfirmed.inputs.iter() {
+            self.spent.insert(input.clone(), unconfirmed.hash.clone());
+        }
+        self.by_time.insert((unconfirmed.time, unconfirmed.hash.clone()));
+        self.by_deadline.insert((unconfirmed.deadline, unconfirmed.hash.clone()));
+    }
+
+    // remove tx from all indexes
+    fn unregister_indexes(&mut self, unconfirmed: &Unconfirmed<T>) {
+        self.version = self.version.wrapping_add(1);
+        self.positions.remove(&unconfirmed.hash);
+        for depend in unconfirmed.depends.iter() {
+            let is_empty = match self.children.get_mut(depend) {
+                Some(children) => {
+                    children.retain(|hash| hash != &unconfirmed.hash);
+                    children.len() == 0
+                },
+                None => false,
+            };
+            if is_empty {
+                self.children.remove(depend);
+            }
+        }
+        for input in unconfirmed.inputs.iter() {
+            if self.spent.get(input) == Some(&unconfirmed.hash) {
+                self.spent.remove(input);
+            }
+        }
+        self.by_time.remove(&(unconfirmed.time, unconfirmed.hash.clone()));
+        self.by_deadline.remove(&(unconfirmed.deadline, unconfirmed.hash.clone()));
+    }
+
+    // error when the tx is inserted or depends on own descendant in pool
+    // (descendant arrived before the tx lists it as depend)
+    fn check_cyclic(&self, unconfirmed: &Unconfirmed<T>) -> Result<(), PoolError> {
+        if self.position(&unconfirmed.hash).is_some() {
+            return Err(PoolError::AlreadyInserted);
+        }
+        let descendants = self.collect_depend_myself(&[unconfirmed.hash.clone()]);
+        if unconfirmed.depends.iter().any(|hash| descendants.contains(hash)) {
+            return Err(PoolError::CyclicDepends);
+        }
+        Ok(())
+    }
+
+    // push unconfirmed tx already passed check_cyclic
+    // txs depend on it (arrived before) are moved after it, return inserted tx's index
+    fn push_unconfirmed(&mut self, unconfirmed: Unconfirmed<T>) -> usize {
+        let children = self.children.get(&unconfirmed.hash).cloned().unwrap_or_default();
+        let moved = self.take_with_descendants(&children);
+        let index = self.insert_ordered(unconfirmed);
+        self.insert_all(moved);
+        index
+    }
+}
+
+
+// order txs parents first keeping listed order as possible
+// return (ordered, txs in cycle)
+fn sort_parents_first<T>(txs: Vec<Unconfirmed<T>>) -> (Vec<Unconfirmed<T>>, Vec<Unconfirmed<T>>) {
+    let positions: HashMap<U256, usize> = txs.iter()
+        .enumerate()
+        .map(|(index, tx)| (tx.hash.clone(), index))
+        .collect();
+    let mut waiting = vec![0usize; txs.len()];
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); txs.len()];
+    for (index, tx) in txs.iter().enumerate() {
+        for depend in tx.depends.iter() {
+            if let Some(parent) = positions.get(depend) {
+                waiting[index] += 1;
+                children[*parent].push(index);
+            }
+        }
+    }
+    let mut queue: VecDeque<usize> = (0..txs.len()).filter(|index| waiting[*index] == 0).collect();
+    let mut order = Vec::with_capacity(txs.len());
+    while let Some(index) = queue.pop_front() {
+        order.push(index);
+        for child in children[index].iter() {
+            waiting[*child] -= 1;
+            if waiting[*child] == 0 {
+                queue.push_back(*child);
+            }
+        }
+    }
+    let mut txs: Vec<Option<Unconfirmed<T>>> = txs.into_iter().map(Some).collect();
+    let ordered = order.into_iter().filter_map(|index| txs[index].take()).collect();
+    let cyclic = txs.into_iter().filter_map(|tx| tx).collect();
+    (ordered, cyclic)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xorshift, deterministic by seed
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+    }
+
+    #[derive(Clone)]
+    struct ModelTx {
+        hash: u64,
+        depends: Vec<u64>,
+        inputs: Vec<(u64, u32)>,
+        price: u32,
+        time: u32,
+        deadline: u32,
+        size: u32,
+    }
+
+    impl ModelTx {
+        fn random(rng: &mut Rng) -> Self {
+            // depends only on smaller hash to avoid cycles, parent may arrive later
+            let hash = 1 + rng.below(40);
+            let depends = (0..rng.below(3))
+                .filter_map(|_| if 1 < hash { Some(1 + rng.below(hash - 1)) } else { None })
+                .collect();
+            let inputs = (0..1 + rng.below(2))
+                .map(|_| (100 + rng.below(20), rng.below(2) as u32))
+                .collect();
+            ModelTx {
+                hash,
+                depends,
+                inputs,
+                price: rng.below(6) as u32,
+                time: rng.below(10) as u32,
+                deadline: rng.below(100) as u32,
+                size: 1 + rng.below(300) as u32,
+            }
+        }
+
+        fn unconfirmed(&self) -> Unconfirmed<u64> {
+            Unconfirmed::new(
+                self.hash,
+                U256::from(self.hash),
+                self.depends.iter().map(|hash| U256::from(*hash)).collect(),
+                self.price,
+                self.time,
+                self.deadline,
+                self.size,
+                self.inputs.iter().map(|(hash, index)| (U256::from(*hash), *index)).collect())
+        }
+
+        fn fee(&self) -> u64 {
+            self.price as u64 * self.size as u64
+        }
+    }
+
+    // txs with all descendants in model
+    fn with_descendants(model: &[ModelTx], roots: &[u64]) -> Vec<u64> {
+        let mut collected: Vec<u64> = Vec::new();
+        let mut stack = roots.to_vec();
+        while let Some(hash) = stack.pop() {
+            if collected.contains(&hash) || !model.iter().any(|tx| tx.hash == hash) {
+                continue;
+            }
+            collected.push(hash);
+            stack.extend(model.iter()
+                .filter(|tx| tx.depends.contains(&hash))
+                .map(|tx| tx.hash));
+        }
+        collected
+    }
+
+    fn model_push(model: &mut Vec<ModelTx>, tx: &ModelTx) -> bool {
+        if model.iter().any(|_tx| _tx.hash == tx.hash) {
+            return false;
+        }
+        let conflicts: Vec<u64> = model.iter()
+            .filter(|_tx| _tx.inputs.iter().any(|input| tx.inputs.contains(input)))
+            .map(|_tx| _tx.hash)
+            .collect();
+        let collected = with_descendants(model, &conflicts);
+        if tx.depends.iter().any(|hash| collected.contains(hash)) {
+            return false;
+        }
+        let conflict_fee: u64 = model.iter()
+            .filter(|_tx| collected.contains(&_tx.hash))
+            .map(|_tx| _tx.fee())
+            .sum();
+        if 0 < conflicts.len() && tx.fee() <= conflict_fee + REPLACE_PRICE_INCREMENT * tx.size as u64 {
+            return false;
+        }
+        model.retain(|_tx| !collected.contains(&_tx.hash));
+        model.push(tx.clone());
+        true
+    }
+
+    fn check_same(pool: &TxPool<u64>, model: &[ModelTx]) {
+        pool.check_consistency().unwrap();
+        let mut hashs: Vec<u64> = pool.iter().map(|tx| tx.obj).collect();
+        let mut expected: Vec<u64> = model.iter().map(|tx| tx.hash).collect();
+        hashs.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(hashs, expected);
+    }
+
+    #[test]
+    fn randomized_operations() {
+        for seed in 1..200u64 {
+            let mut rng = Rng(seed.wrapping_mul(0x9E3779B97F4A7C15));
+            let mut pool: TxPool<u64> = TxPool::with_limits(0, PoolLimits::unlimited());
+            let mut model: Vec<ModelTx> = Vec::new();
+            for _ in 0..150 {
+                match rng.below(12) {
+                    0..=5 => {
+                        let tx = ModelTx::random(&mut rng);
+                        let accepted = model_push(&mut model, &tx);
+                        assert_eq!(pool.push(tx.unconfirmed()).is_ok(), accepted, "seed {}", seed);
+                    },
+                    6 => {
+                        let hash = 1 + rng.below(40);
+                        let exist = model.iter().any(|tx| tx.hash == hash);
+                        model.retain(|tx| tx.hash != hash);
+                        assert_eq!(pool.remove(&U256::from(hash)).is_ok(), exist, "seed {}", seed);
+                    },
+                    7 => {
+                        let hashs: Vec<u64> = (0..rng.below(4)).map(|_| 1 + rng.below(40)).collect();
+                        model.retain(|tx| !hashs.contains(&tx.hash));
+                        let hashs: Vec<U256> = hashs.iter().map(|hash| U256::from(*hash)).collect();
+                        pool.remove_many(&hashs);
+                    },
+                    8 => {
+                        let hash = 1 + rng.below(40);
+                        let collected = with_descendants(&model, &[hash]);
+                        model.retain(|tx| !collected.contains(&tx.hash));
+                        let changes = pool.remove_with_depends(&U256::from(hash));
+                        assert_eq!(changes.map(|c| c.removed.len()).unwrap_or(0), collected.len(), "seed {}", seed);
+                    },
+                    9 => {
+                        let deadline = rng.below(30) as u32;
+                        let expired: Vec<u64> = model.iter()
+                            .filter(|tx| tx.deadline < deadline)
+                            .map(|tx| tx.hash)
+                            .collect();
+                        let collected = with_descendants(&model, &expired);
+                        model.retain(|tx| !collected.contains(&tx.hash));
+                        pool.clear_by_deadline(deadline);
+                    },
+                    10 => {
+                        let (now, max_age) = (10 + rng.below(5) as u32, 8);
+                        let expired: Vec<u64> = model.iter()
+                            .filter(|tx| tx.time + max_age < now)
+                            .map(|tx| tx.hash)
+                            .collect();
+                        let collected = with_descendants(&model, &expired);
+                        model.retain(|tx| !collected.contains(&tx.hash));
+                        pool.expire(now, max_age);
+                    },
+                    _ => {
+                        // only ordering is changed
+                        let hash = 1 + rng.below(40);
+                        pool.prioritise(&U256::from(hash), rng.below(7) as i64 - 3);
+                    },
+                }
+                check_same(&pool, &model);
+            }
+        }
+    }
+
+    #[test]
+    fn consistency_detects_broken_order() {
+        let mut pool: TxPool<u64> = TxPool::new(0);
+        for (hash, price) in [(1u64, 5u32), (2, 3), (3, 1)].iter() {
+            let tx = ModelTx {hash: *hash, depends: vec![], inputs: vec![(100 + hash, 0)],
+                price: *price, time: 0, deadline: 0, size: 1};
+            pool.push(tx.unconfirmed()).unwrap();
+        }
+        pool.check_consistency().unwrap();
+        pool.unconfirmed.swap(0, 2);
+        assert!(pool.check_consistency().is_err());
+    }
+
+    #[test]
+    fn limits_reject_long_chain() {
+        let limits = PoolLimits {max_ancestors: 3, max_ancestor_size: 1000, max_descendants: 3, max_descendant_size: 1000};
+        let mut pool: TxPool<u64> = TxPool::with_limits(0, limits.clone());
+        let chain = |hash: u64, size: u32| ModelTx {hash, depends: vec![hash - 1], inputs: vec![(100 + hash, 0)],
+            price: 1, time: 0, deadline: 0, size};
+        for hash in 1..4u64 {
+            pool.push(chain(hash, 10).unconfirmed()).unwrap();
+        }
+        assert_eq!(pool.push(chain(4, 10).unconfirmed()).err(),
+                   Some(PoolError::TooManyAncestors {count: 4, limit: 3}));
+        // descendants of tx1 are 1,2,3 already
+        let sibling = ModelTx {hash: 5, depends: vec![1], inputs: vec![(105, 0)], price: 1, time: 0, deadline: 0, size: 10};
+        assert!(pool.push(sibling.unconfirmed()).err().unwrap().is_limit());
+        // huge tx breaks size limit
+        pool.remove_with_depends(&U256::from(3u64)).unwrap();
+        assert_eq!(pool.push(chain(3, 990).unconfirmed()).err(),
+                   Some(PoolError::AncestorSizeTooLarge {size: 1010, limit: 1000}));
+        pool.check_consistency().unwrap();
+
+        // batch insert rejects violators and txs depend on them
+        let mut pool: TxPool<u64> = TxPool::with_limits(0, limits);
+        let txs = (1..6u64).map(|hash| chain(hash, 10).unconfirmed()).collect();
+        let (rejected, changes) = pool.insert_batch(txs);
+        let rejected: Vec<u64> = rejected.into_iter().map(|tx| tx.obj).collect();
+        assert_eq!(rejected, vec![4, 5]);
+        assert_eq!(changes.added.len(), 3);
+        pool.check_consistency().unwrap();
+    }
+
+    #[test]
+    fn prioritise_saturates() {
+        let mut pool: TxPool<u64> = TxPool::new(0);
+        let tx = ModelTx {hash: 1, depends: vec![], inputs: vec![], price: 5, time: 0, deadline: 0, size: 1};
+        pool.push(tx.unconfirmed()).unwrap();
+        let hash = U256::from(1u64);
+        assert_eq!(pool.prioritise(&hash, i64::max_value()).0, i64::max_value());
+        assert_eq!(pool.prioritise(&hash, 1).0, i64::max_value());
+        assert_eq!(pool.effective_price(pool.get(&hash).unwrap()), u32::max_value());
+        assert_eq!(pool.prioritise(&hash, i64::min_value()).0, -1);
+        assert_eq!(pool.effective_price(pool.get(&hash).unwrap()), 4);
+        pool.prioritise(&hash, i64::min_value());
+        assert_eq!(pool.effective_price(pool.get(&hash).unwrap()), 0);
+        pool.check_consistency().unwrap();
+    }
+
+    #[test]
+    fn remove_long_chain() {
+        let mut pool: TxPool<u64> = TxPool::with_limits(0, PoolLimits::unlimited());
+        let txs = (1..10000u64)
+            .map(|hash| ModelTx {hash, depends: vec![hash - 1], inputs: vec![], price: 1, time: 0, deadline: 0, size: 1})
+            .map(|tx| tx.unconfirmed())
+            .collect();
+        let (rejected, _) = pool.insert_batch(txs);
+        assert_eq!(rejected.len(), 0);
+        let changes = pool.remove_with_depends(&U256::from(1u64)).unwrap();
+        assert_eq!(changes.removed.len(), 9999);
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn orphans_promoted_or_reported() {
+        let limits = PoolLimits {max_ancestors: 2, max_ancestor_size: 1000, max_descendants: 25, max_descendant_size: 1000};
+        let mut pool: TxPool<u64> = TxPool::with_limits(10, limits);
+        let tx = |hash: u64, depends: Vec<u64>| ModelTx {hash, depends, inputs: vec![(100 + hash, 0)],
+            price: 1, time: 0, deadline: 0, size: 1};
+        // parent 9 is already confirmed but listed
+        assert_eq!(pool.push(tx(1, vec![9]).unconfirmed()).unwrap().0, None);
+        let changes = pool.mark_confirmed(&[U256::from(9u64)]);
+        assert_eq!(changes.added, vec![U256::from(1u64)]);
+        assert_eq!(pool.orphans().len(), 0);
+        // orphan over ancestor limit when promoted
+        pool.push(tx(2, vec![1]).unconfirmed()).unwrap();
+        assert_eq!(pool.push(tx(3, vec![2, 8]).unconfirmed()).unwrap().0, None);
+        let changes = pool.mark_confirmed(&[U256::from(8u64)]);
+        assert_eq!(changes.added.len(), 0);
+        assert_eq!(changes.removed.len(), 1);
+        assert_eq!(changes.removed[0].0.obj, 3);
+        assert_eq!(changes.removed[0].1, RemoveReason::Evicted);
+        pool.check_consistency().unwrap();
+    }
+
+    fn sample_entries() -> Vec<DumpEntry> {
+        (1..4u64)
+            .map(|hash| DumpEntry {
+                hash: U256::from(hash),
+                depends: (1..hash).map(U256::from).collect(),
+                inputs: vec![(U256::from(100 + hash), hash as u32)],
+                price: hash as u32,
+                time: 10,
+                deadline: 20,
+                size: 30,
+                blob: vec![hash as u8; hash as usize],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn self_and_cyclic_depends() {
+        let mut pool: TxPool<u64> = TxPool::with_limits(0, PoolLimits::unlimited());
+        let tx = |hash: u64, depends: Vec<u64>| ModelTx {hash, depends, inputs: vec![(100 + hash, 0)],
+            price: hash as u32, time: 0, deadline: 0, size: 1};
+        // own hash is ignored
+        assert_eq!(pool.push(tx(1, vec![1]).unconfirmed()).unwrap().0, Some(0));
+        assert_eq!(pool.get(&U256::from(1u64)).unwrap().depends.len(), 0);
+        // 2 arrived before its depend 3, and 3 depends on 2
+        pool.push(tx(2, vec![3]).unconfirmed()).unwrap();
+        assert_eq!(pool.push(tx(3, vec![2]).unconfirmed()).err(), Some(PoolError::CyclicDepends));
+        let (rejected, _) = pool.insert_batch(vec![tx(4, vec![5]).unconfirmed(), tx(5, vec![4]).unconfirmed(),
+                                                   tx(6, vec![4]).unconfirmed(), tx(7, vec![1]).unconfirmed()]);
+        let mut rejected: Vec<u64> = rejected.iter().map(|tx| tx.obj).collect();
+        rejected.sort_unstable();
+        assert_eq!(rejected, vec![4, 5, 6]);
+        assert_eq!(pool.len(), 3);
+        pool.check_consistency().unwrap();
+    }
+
+    #[test]
+    fn push_many_incremental() {
+        let mut rng = Rng(0x9E3779B97F4A7C15);
+        let mut pool: TxPool<u64> = TxPool::with_limits(0, PoolLimits::unlimited());
+        for hash in 1..5001u64 {
+            // some txs arrive before their parents
+            let depends = if hash % 7 == 0 { vec![hash + 3] } else if hash % 5 == 0 { vec![hash - 1] } else { vec![] };
+            let tx = ModelTx {hash, depends, inputs: vec![(hash, 0)], price: rng.below(50) as u32,
+                time: rng.below(100) as u32, deadline: 0, size: 1};
+            pool.push(tx.unconfirmed()).unwrap();
+        }
+        assert_eq!(pool.len(), 5000);
+        pool.check_consistency().unwrap();
+    }
+
+    #[test]
+    fn dump_round_trip() {
+        let entries = sample_entries();
+        let decoded = decode_dump(&encode_dump(&entries)).unwrap();
+        assert_eq!(decoded.len(), entries.len());
+        for (a, b) in entries.iter().zip(decoded.iter()) {
+            assert_eq!(a.hash, b.hash);
+            assert_eq!(a.depends, b.depends);
+            assert_eq!(a.inputs, b.inputs);
+            assert_eq!((a.price, a.time, a.deadline, a.size), (b.price, b.time, b.deadline, b.size));
+            assert_eq!(a.blob, b.blob);
+        }
+        assert_eq!(decode_dump(&encode_dump(&[])).unwrap().len(), 0);
+        // empty entry is the smallest
+        let empty = DumpEntry {hash: U256::from(1u64), depends: vec![], inputs: vec![],
+            price: 0, time: 0, deadline: 0, size: 0, blob: vec![]};
+        let size = encode_dump(&[empty]).len() - encode_dump(&[]).len();
+        assert_eq!(size, DUMP_ENTRY_MIN_SIZE);
+    }
+
+    #[test]
+    fn dump_rejects_broken() {
+        let buf = encode_dump(&sample_entries());
+        // any flipped byte breaks checksum
+        for index in [0, 10, buf.len() / 2, buf.len() - 1].iter() {
+            let mut broken = buf.clone();
+            broken[*index] ^= 1;
+            assert_eq!(decode_dump(&broken).err().unwrap(), "dump checksum is not match");
+        }
+        assert!(decode_dump(&buf[..buf.len() - 1]).is_err());
+        // other version with valid checksum
+        let mut body = buf[..buf.len() - 32].to_vec();
+        body[4..8].copy_from_slice(&u32_to_bytes(DUMP_VERSION + 1));
+        let checksum = sha256double(&body);
+        body.extend_from_slice(&checksum);
+        assert_eq!(decode_dump(&body).err().unwrap(), format!("not supported dump version {}", DUMP_VERSION + 1));
+        // huge count is not trusted
+        let mut body = buf[..buf.len() - 32].to_vec();
+        body[8..12].copy_from_slice(&u32_to_bytes(u32::max_value()));
+        let checksum = sha256double(&body);
+        body.extend_from_slice(&checksum);
+        assert!(decode_dump(&body).err().unwrap().starts_with("unexpected end of dump"));
+    }
+
+    #[test]
+    fn short_ids_resolve() {
+        let mut pool: TxPool<u64> = TxPool::new(0);
+        for hash in 1..50u64 {
+            let tx = ModelTx {hash, depends: vec![], inputs: vec![], price: 1, time: 0, deadline: 0, size: 1};
+            pool.push(tx.unconfirmed()).unwrap();
+        }
+        let index = pool.short_id_index(7, 11);
+        assert_eq!(index.len(), 49);
+        let short_ids = vec![short_id(7, 11, &U256::from(3u64)), short_id(7, 11, &U256::from(100u64))];
+        let found: Vec<Option<u64>> = pool.resolve_short_ids(&index, &short_ids).iter()
+            .map(|tx| tx.map(|tx| tx.obj))
+            .collect();
+        assert_eq!(found, vec![Some(3), None]);
+        assert!(short_ids.iter().all(|id| *id <= SHORT_ID_MASK));
+        // duplicated ids in a block are all missing
+        let short_ids = vec![short_id(7, 11, &U256::from(3u64)), short_id(7, 11, &U256::from(4u64)),
+                             short_id(7, 11, &U256::from(3u64))];
+        let found: Vec<Option<u64>> = pool.resolve_short_ids(&index, &short_ids).iter()
+            .map(|tx| tx.map(|tx| tx.obj))
+            .collect();
+        assert_eq!(found, vec![None, Some(4), None]);
+
+        // block of 5 txs, position 0 and 3 are prefilled
+        let short_ids = vec![short_id(7, 11, &U256::from(5u64)), short_id(7, 11, &U256::from(100u64)),
+                             short_id(7, 11, &U256::from(6u64))];
+        let (txs, missing) = pool.reconstruct(&index, &short_ids, &[3, 0]).unwrap();
+        let txs: Vec<Option<u64>> = txs.iter().map(|tx| tx.map(|tx| tx.obj)).collect();
+        assert_eq!(txs, vec![None, Some(5), None, None, Some(6)]);
+        assert_eq!(missing, vec![2]);
+        assert!(pool.reconstruct(&index, &short_ids, &[0, 0]).is_err());
+        assert!(pool.reconstruct(&index, &short_ids, &[5]).is_err());
+    }
+}
@@ -0,0 +1,52 @@
+use sha2::{Sha256, Digest};
+use ripemd160::Ripemd160;
+
+
+#[inline]
+pub fn u32_to_bytes(i: u32) -> [u8;4] {
+    i.to_le_bytes()
+}
+
+#[inline]
+pub fn bytes_to_u32(bytes: &[u8]) -> u32 {
+    let mut tmp= [0u8;4];
+    for (a, b) in tmp.iter_mut().zip(bytes.iter()) {
+        *a = *b
+    }
+    u32::from_le_bytes(tmp)
+}
+
+#[inline]
+pub fn sha256double(b: &[u8]) -> Vec<u8> {
+    let hash = Sha256::digest(b);
+    let hash = Sha256::digest(hash.as_slice());
+    hash.to_vec()
+}
+
+#[inline]
+pub fn hash160(b: &[u8]) -> Vec<u8> {
+    // ripemd160(sha256(b)), same as bc4py address identifier
+    let hash = Sha256::digest(b);
+    let hash = Ripemd160::digest(hash.as_slice());
+    hash.to_vec()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i+2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn hash160_known_answer() {
+        // secp256k1 generator point compressed public key
+        let pubkey = from_hex("0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798");
+        assert_eq!(hash160(&pubkey), from_hex("751e76e8199196d454941c45d1b3a323f1433bd6"));
+        assert_eq!(hash160(b"").len(), 20);
+    }
+}
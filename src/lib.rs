@@ -11,11 +11,15 @@ extern crate sha2;
 
 pub mod pyaddress;
 pub mod pyiter;
+pub mod pybloom;
 pub mod pymodules;
 pub mod mempool;
 pub mod workhash;
 pub mod utils;
 
+// engine modules live in bc4py_core (core/)
+pub use bc4py_core::{txpool, iblt, bloom, bech, fee};
+
 
 #[cfg(test)]
 mod tests {
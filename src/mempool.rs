@@ -1,58 +1,103 @@
-use crate::pyiter::PyIter;
+use crate::pyiter::{PyIter, PyView};
+use crate::pybloom::PyBloomFilter;
+use crate::fee::MAX_TARGET_BLOCKS;
+use crate::iblt::{Iblt, MAX_CELLS};
+use crate::utils::{get_current_time, bytes_to_u32};
+use crate::txpool::{TxPool, Unconfirmed, Changes, RemoveReason, PoolError, PoolLimits, ShortIdIndex, Query, encode_dump, decode_dump};
 use pyo3::prelude::*;
-use pyo3::exceptions::AssertionError;
-use pyo3::types::{PyBytes, PyTuple};
-use pyo3::PyObjectProtocol;
-use std::cmp::PartialEq;
+use pyo3::exceptions::{AssertionError, ValueError, IOError, RuntimeError};
+use pyo3::types::{PyBytes, PyTuple, PyList, PyDict};
+use pyo3::{PyObjectProtocol, PyIterProtocol, PyMappingProtocol};
+use pyo3::create_exception;
+use std::sync::{Arc, RwLock, RwLockReadGuard, Mutex, MutexGuard, TryLockError};
 use bigint::U256;
 
 
-// NO COPY TRAIT!
-struct Unconfirmed {
-    // store TX object
-    obj: PyObject,
-    // for find priority
-    hash: U256,
-    depends: Box<[U256]>,
-    price: u32,
-    time: u32,
-    deadline: u32,
-    size: u32,
+// default lower bounds of stats() price histogram
+const HISTOGRAM_BUCKETS: &[u32] = &[0, 1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 2000, 5000, 10000];
+// callback events
+const EVENT_ADDED: &str = "added";
+const EVENT_REMOVED: &str = "removed";
+const EVENT_REORDERED: &str = "reordered";
+// lock is poisoned only by panic while locked
+const POISONED_ERROR: &str = "mempool lock is poisoned";
+
+
+// raised when push violates ancestor/descendant limits
+create_exception!(bc4py_extension, PackageLimitError, AssertionError);
+
+// read lock with GIL, writer (runs with GIL released) is waited without GIL
+// so other python threads are not stalled, poisoned lock is error instead of panic
+fn read_pool<'a>(py: Python, core: &'a RwLock<TxPool<PyObject>>) -> PyResult<RwLockReadGuard<'a, TxPool<PyObject>>> {
+    loop {
+        match core.try_read() {
+            Ok(pool) => return Ok(pool),
+            Err(TryLockError::Poisoned(_)) => return Err(RuntimeError::py_err(POISONED_ERROR)),
+            Err(TryLockError::WouldBlock) => {
+                // wait until writer finish, then try again with GIL
+                py.allow_threads(move || {
+                    let _ = core.read();
+                });
+            },
+        }
+    }
 }
 
-impl Unconfirmed {
-    pub fn bytes(&self, py: Python) -> PyObject {
-        let mut slice = [0u8;32];
-        self.hash.to_big_endian(&mut slice);
-        PyBytes::new(py, &slice).to_object(py)
+fn pool_error(err: PoolError) -> PyErr {
+    if err.is_limit() {
+        PackageLimitError::py_err(err.to_string())
+    } else {
+        AssertionError::py_err(err.to_string())
     }
 }
 
-impl PartialEq for Unconfirmed {
-    fn eq(&self, other: &Unconfirmed) -> bool {
-        self.hash == other.hash
-    }
+fn u256_to_bytes(py: Python, hash: &U256) -> PyObject {
+    let mut slice = [0u8;32];
+    hash.to_big_endian(&mut slice);
+    PyBytes::new(py, &slice).to_object(py)
+}
+
+type UnconfirmedArgs<'a> = (&'a PyAny, &'a PyBytes, Vec<&'a PyBytes>, u32, u32, u32, u32, Vec<(&'a PyBytes, u32)>);
+
+fn new_unconfirmed(py: Python, obj: &PyAny, hash: &PyBytes, depends: Vec<&PyBytes>, price: u32, time: u32, deadline: u32, size: u32,
+                   inputs: Vec<(&PyBytes, u32)>) -> Unconfirmed<PyObject> {
+    let hash = U256::from(hash.as_bytes());
+    let depends: Vec<U256> = depends.iter()
+        .map(|hash| U256::from(hash.as_bytes())).collect();
+    let inputs: Vec<(U256, u32)> = inputs.iter()
+        .map(|(hash, index)| (U256::from(hash.as_bytes()), *index)).collect();
+    Unconfirmed::new(obj.to_object(py), hash, depends, price, time, deadline, size, inputs)
 }
 
+
 #[pyclass]
 pub struct MemoryPool {
-    // pre-ordered high to low
-    unconfirmed: Vec<Unconfirmed>,
+    // shared with rust side
+    core: Arc<RwLock<TxPool<PyObject>>>,
+    // (event, callback), not borrowed mutably while GIL is released
+    callbacks: Mutex<Vec<(String, PyObject)>>,
+    // built by short_id_index for reconstruct
+    short_ids: Mutex<Option<ShortIdIndex>>,
 }
 
 #[pyproto]
 impl PyObjectProtocol for MemoryPool {
     fn __repr__(&self) -> PyResult<String> {
-        Ok(format!("<MemPool len={}>", self.unconfirmed.len()))
+        let py = unsafe { Python::assume_gil_acquired() };
+        Ok(format!("<MemPool len={}>", self.pool(py)?.len()))
     }
 }
 
 #[pymethods]
 impl MemoryPool {
     #[new]
-    fn new() -> Self {
+    #[args(orphan_limit="0", max_ancestors="25", max_ancestor_size="101000", max_descendants="25", max_descendant_size="101000")]
+    fn new(orphan_limit: usize, max_ancestors: usize, max_ancestor_size: u64, max_descendants: usize, max_descendant_size: u64) -> Self {
+        let limits = PoolLimits {max_ancestors, max_ancestor_size, max_descendants, max_descendant_size};
         MemoryPool {
-            unconfirmed: Vec::new(),
+            core: Arc::new(RwLock::new(TxPool::with_limits(orphan_limit, limits))),
+            callbacks: Mutex::new(Vec::new()),
+            short_ids: Mutex::new(None),
         }
     }
 
@@ -61,88 +106,149 @@ impl MemoryPool {
     /// --
     ///
     /// get TX object by hash
-    fn get_obj(&self, py: Python, hash: &PyBytes) -> Option<PyObject> {
+    fn get_obj(&self, py: Python, hash: &PyBytes) -> PyResult<Option<PyObject>> {
         let hash = U256::from(hash.as_bytes());
-        let index = self.unconfirmed.iter()
-            .position(|tx| hash == tx.hash)?;
-        self.unconfirmed.get(index).map(|tx| tx.obj.clone_ref(py))
+        Ok(self.pool(py)?.get(&hash).map(|tx| tx.obj.clone_ref(py)))
     }
 
     /// exist(hash: bytes) -> bool
     /// --
     ///
     /// check hash is exist
-    fn exist(&self, hash: &PyBytes) -> bool {
+    fn exist(&self, py: Python, hash: &PyBytes) -> PyResult<bool> {
         let hash = U256::from(hash.as_bytes());
-        self.unconfirmed.iter()
-            .position(|tx| hash == tx.hash)
-            .is_some()
+        Ok(self.pool(py)?.position(&hash).is_some())
     }
 
     /// length() -> int
     /// --
     ///
     /// return unconfirmed tx's length
-    fn length(&self) -> usize {
-        self.unconfirmed.len()
+    fn length(&self, py: Python) -> PyResult<usize> {
+        Ok(self.pool(py)?.len())
     }
 
     /// position(hash: bytes) -> int
     /// --
     ///
     /// the unconfirmed tx's position (this means tx's priority)
-    fn position(&self, hash: &PyBytes) -> Option<usize> {
+    fn position(&self, py: Python, hash: &PyBytes) -> PyResult<Option<usize>> {
         let hash = U256::from(hash.as_bytes());
-        self.unconfirmed.iter()
-            .position(|tx| hash == tx.hash)
+        Ok(self.pool(py)?.position(&hash))
     }
 
-    /// push(obj: TX, hash: bytes, depends: list, price: int, time: int, deadline: int, size: int) -> int
+    /// push(obj: TX, hash: bytes, depends: list, price: int, time: int, deadline: int, size: int, inputs: list=None, keys: list=None) -> Optional[int]
     /// --
     ///
     /// push new unconfirmed tx, return inserted index
-    fn push(&mut self, obj: &PyAny, hash: &PyBytes, depends: Vec<&PyBytes>, price: u32, time: u32, deadline: u32, size: u32)
-        -> PyResult<usize> {
+    /// depends is unconfirmed parents only, do not list parents already in blocks
+    /// inputs is spent outpoints list of (txhash, index)
+    /// conflicted tx is replaced only when new tx pays more fee than conflicts with depends
+    /// when orphan_limit is enabled, tx with unknown depends is held as orphan (index is None)
+    /// and orphans are promoted automatically when the parents arrive
+    /// raise PackageLimitError when ancestors or descendants exceed the pool limits
+    /// keys is extra bloom filter keys for filter_matching (ex. addresses)
+    #[args(inputs="None", keys="None")]
+    fn push(&self, py: Python, obj: &PyAny, hash: &PyBytes, depends: Vec<&PyBytes>, price: u32, time: u32, deadline: u32, size: u32,
+            inputs: Option<Vec<(&PyBytes, u32)>>, keys: Option<Vec<&PyBytes>>) -> PyResult<Option<usize>> {
+        let unconfirmed = new_unconfirmed(
+            py, obj, hash, depends, price, time, deadline, size, inputs.unwrap_or_default());
+        let (index, _changes) = self.push_unconfirmed(py, unconfirmed, keys)?;
+        Ok(index)
+    }
+
+    /// push_replace(obj: TX, hash: bytes, depends: list, price: int, time: int, deadline: int, size: int, inputs: list=None, keys: list=None) -> Tuple[Optional[int], Tuple[TX]]
+    /// --
+    ///
+    /// same as push but return inserted index and replaced conflict txs
+    #[args(inputs="None", keys="None")]
+    fn push_replace(&self, py: Python, obj: &PyAny, hash: &PyBytes, depends: Vec<&PyBytes>, price: u32, time: u32, deadline: u32,
+                    size: u32, inputs: Option<Vec<(&PyBytes, u32)>>, keys: Option<Vec<&PyBytes>>) -> PyResult<PyObject> {
+        let unconfirmed = new_unconfirmed(
+            py, obj, hash, depends, price, time, deadline, size, inputs.unwrap_or_default());
+        let (index, changes) = self.push_unconfirmed(py, unconfirmed, keys)?;
+        let replaced: Vec<PyObject> = changes.into_removed(RemoveReason::Conflict)
+            .into_iter().map(|tx| tx.obj).collect();
+        Ok(PyTuple::new(py, &[
+            index.to_object(py),
+            PyTuple::new(py, &replaced).to_object(py),
+        ]).to_object(py))
+    }
+
+    /// orphan_length() -> int
+    /// --
+    ///
+    /// return orphan tx's length
+    fn orphan_length(&self, py: Python) -> PyResult<usize> {
+        Ok(self.pool(py)?.orphans().len())
+    }
+
+    /// is_orphan(hash: bytes) -> bool
+    /// --
+    ///
+    /// check hash is held as orphan
+    fn is_orphan(&self, py: Python, hash: &PyBytes) -> PyResult<bool> {
         let hash = U256::from(hash.as_bytes());
-        let mut depends: Vec<U256> = depends.iter()
-            .map(|hash| U256::from(hash.as_bytes())).collect();
-
-        // remove duplicate depends
-        depends.sort_unstable();
-        depends.dedup();
-        // drop any excess capacity
-        let depends = depends.into_boxed_slice();
-
-        // generate tx object
-        let unconfirmed = {
-            let gil = Python::acquire_gil();
-            let obj = obj.to_object(gil.python());
-            Unconfirmed {obj, hash, depends, price, time, deadline, size}
-        };
+        Ok(self.pool(py)?.orphans().iter()
+            .position(|tx| hash == tx.hash)
+            .is_some())
+    }
 
-        // push
-        self.push_unconfirmed(unconfirmed)
+    /// list_orphan_hash() -> Tuple[bytes]
+    /// --
+    ///
+    /// all orphan tx's hash tuple ordered by arrival
+    fn list_orphan_hash(&self, py: Python) -> PyResult<PyObject> {
+        let outputs: Vec<PyObject> = self.pool(py)?.orphans()
+            .iter()
+            .map(|tx| u256_to_bytes(py, &tx.hash))
+            .collect();
+        Ok(PyTuple::new(py, &outputs).to_object(py))
+    }
+
+    /// spender_of(txhash: bytes, index: int) -> Optional[bytes]
+    /// --
+    ///
+    /// find unconfirmed tx's hash which spend the outpoint
+    fn spender_of(&self, py: Python, txhash: &PyBytes, index: u32) -> PyResult<Option<PyObject>> {
+        let txhash = U256::from(txhash.as_bytes());
+        Ok(self.pool(py)?.spender_of(&(txhash, index))
+            .map(|hash| u256_to_bytes(py, hash)))
+    }
+
+    /// conflicts(outpoints: list) -> List[bytes]
+    /// --
+    ///
+    /// find unconfirmed tx's hashes which spend any of (txhash, index) outpoints
+    fn conflicts(&self, py: Python, outpoints: Vec<(&PyBytes, u32)>) -> PyResult<PyObject> {
+        let outpoints: Vec<(U256, u32)> = outpoints.iter()
+            .map(|(hash, index)| (U256::from(hash.as_bytes()), *index)).collect();
+        let hashs: Vec<PyObject> = self.pool(py)?.find_conflicts(&outpoints)
+            .iter().map(|hash| u256_to_bytes(py, hash)).collect();
+        Ok(PyList::new(py, &hashs).to_object(py))
+    }
+
+    /// prioritise(hash: bytes, price_delta: int) -> int
+    /// --
+    ///
+    /// add price delta to the tx's ordering priority without changing its fee
+    /// the tx and its dependency chain are reordered, return accumulated delta
+    /// delta is kept even if the tx is not in pool yet or removed and re-added
+    fn prioritise(&self, py: Python, hash: &PyBytes, price_delta: i64) -> PyResult<i64> {
+        let hash = U256::from(hash.as_bytes());
+        let (delta, changes) = self.with_pool_mut(py, move |pool| pool.prioritise(&hash, price_delta))?;
+        self.emit_changes(py, &changes);
+        Ok(delta)
     }
 
     /// remove(hash: bytes) -> None
     /// --
     ///
     /// simple remove unconfirmed tx
-    fn remove(&mut self, hash: &PyBytes) -> PyResult<()> {
-        // require reorder after remove the hash
+    fn remove(&self, py: Python, hash: &PyBytes) -> PyResult<()> {
         let hash = U256::from(hash.as_bytes());
-        let mut deleted = Vec::with_capacity(1);
-        // remove all related txs
-        self.remove_with_depend_myself(&hash, &mut deleted);
-        if deleted.len() == 0 {
-            return Err(AssertionError::py_err("not found hash"));
-        }
-        // remove root tx
-        assert_eq!(hash, deleted.remove(0).hash);
-        // insert all
-        for tx in deleted {
-            assert!(self.push_unconfirmed(tx).is_ok())
-        }
+        let changes = self.with_pool_mut(py, move |pool| pool.remove(&hash))?.map_err(pool_error)?;
+        self.emit_changes(py, &changes);
         Ok(())
     }
 
@@ -150,246 +256,599 @@ impl MemoryPool {
     /// --
     ///
     /// simple remove unconfirmed txs (no error even if no delete tx)
-    fn remove_many(&mut self, hashs: Vec<&PyBytes>) {
-        //require reorder after remove the hash
+    fn remove_many(&self, py: Python, hashs: Vec<&PyBytes>) -> PyResult<()> {
         let hashs: Vec<U256> = hashs
             .iter().map(|hash| U256::from(hash.as_bytes())).collect();
-        let mut deleted = Vec::with_capacity(hashs.len());
-        // remove all related txs
-        for hash in hashs.iter() {
-            self.remove_with_depend_myself(hash, &mut deleted);
+        let changes = self.with_pool_mut(py, move |pool| pool.remove_many(&hashs))?;
+        self.emit_changes(py, &changes);
+        Ok(())
+    }
+
+    /// block_connected(hashs: list, spent_outpoints: list) -> Tuple[TX]
+    /// --
+    ///
+    /// remove txs included in new block and txs conflict with block's spent outpoints
+    /// return removed conflict txs (included txs are not returned)
+    fn block_connected(&self, py: Python, hashs: Vec<&PyBytes>, spent_outpoints: Vec<(&PyBytes, u32)>) -> PyResult<PyObject> {
+        let hashs: Vec<U256> = hashs
+            .iter().map(|hash| U256::from(hash.as_bytes())).collect();
+        let outpoints: Vec<(U256, u32)> = spent_outpoints.iter()
+            .map(|(hash, index)| (U256::from(hash.as_bytes()), *index)).collect();
+        let changes = self.with_pool_mut(py, move |pool| pool.block_connected(&hashs, &outpoints))?;
+        self.emit_changes(py, &changes);
+
+        // output conflict txs
+        let elements: Vec<PyObject> = changes.into_removed(RemoveReason::Conflict)
+            .into_iter().map(|tx| tx.obj).collect();
+        Ok(PyTuple::new(py, &elements).to_object(py))
+    }
+
+    /// mark_confirmed(hashs: list) -> int
+    /// --
+    ///
+    /// tell parents of orphans are confirmed (ex. orphan listed a parent already in a block)
+    /// orphans waiting only them are promoted, return promoted count
+    fn mark_confirmed(&self, py: Python, hashs: Vec<&PyBytes>) -> PyResult<usize> {
+        let hashs: Vec<U256> = hashs.iter().map(|hash| U256::from(hash.as_bytes())).collect();
+        let changes = self.with_pool_mut(py, move |pool| pool.mark_confirmed(&hashs))?;
+        self.emit_changes(py, &changes);
+        Ok(changes.added.len())
+    }
+
+    /// block_disconnected(txs: list) -> Tuple[TX]
+    /// --
+    ///
+    /// re-insert txs of disconnected block at once
+    /// txs is list of push params (obj, hash, depends, price, time, deadline, size, inputs)
+    /// return txs no longer fit (conflict with pool, over package limits or depend on such tx)
+    fn block_disconnected(&self, py: Python, txs: Vec<UnconfirmedArgs>) -> PyResult<PyObject> {
+        let txs: Vec<Unconfirmed<PyObject>> = txs.into_iter()
+            .map(|(obj, hash, depends, price, time, deadline, size, inputs)| {
+                new_unconfirmed(py, obj, hash, depends, price, time, deadline, size, inputs)
+            })
+            .collect();
+        let (rejected, changes) = self.with_pool_mut(py, move |pool| pool.insert_batch(txs))?;
+        self.emit_changes(py, &changes);
+
+        // output rejected txs
+        let elements: Vec<PyObject> = rejected
+            .into_iter().map(|tx| tx.obj).collect();
+        Ok(PyTuple::new(py, &elements).to_object(py))
+    }
+
+    /// short_id_index(key: bytes) -> int
+    /// --
+    ///
+    /// build compact block short id index of unconfirmed txs and return its size
+    /// key is 16 bytes siphash key (k0 8 bytes little endian + k1 8 bytes little endian)
+    /// short id is lower 6 bytes of siphash-2-4 of txhash, collided ids never match
+    /// pool keeps only one index, next call replaces it (reconstruct blocks one by one)
+    fn short_id_index(&self, py: Python, key: &PyBytes) -> PyResult<usize> {
+        let key = key.as_bytes();
+        if key.len() != 16 {
+            return Err(ValueError::py_err(format!("key is 16 bytes but {} bytes", key.len())));
+        }
+        let k0 = bytes_to_u32(&key[0..4]) as u64 | (bytes_to_u32(&key[4..8]) as u64) << 32;
+        let k1 = bytes_to_u32(&key[8..12]) as u64 | (bytes_to_u32(&key[12..16]) as u64) << 32;
+        let index = self.with_pool(py, move |pool| pool.short_id_index(k0, k1))?;
+        let size = index.len();
+        *self.short_ids.lock().map_err(|_| RuntimeError::py_err(POISONED_ERROR))? = Some(index);
+        Ok(size)
+    }
+
+    /// reconstruct(short_ids: list, prefilled: list) -> Tuple[List[Optional[TX]], List[int]]
+    /// --
+    ///
+    /// fill block txs by short ids with index built by short_id_index
+    /// prefilled is list of (index, obj) with absolute index in block
+    /// return txs list (None is missing) and missing indexes to request
+    /// short ids listed twice are missing because they cannot be told apart
+    fn reconstruct(&self, py: Python, short_ids: Vec<u64>, prefilled: Vec<(usize, PyObject)>) -> PyResult<PyObject> {
+        let positions: Vec<usize> = prefilled.iter().map(|(position, _)| *position).collect();
+        let (mut objs, missing) = {
+            // pool first, may wait without GIL, short ids is locked only with GIL
+            let core = self.pool(py)?;
+            let short_id_index = self.short_ids.lock()
+                .map_err(|_| RuntimeError::py_err(POISONED_ERROR))?;
+            let index = match short_id_index.as_ref() {
+                Some(index) => index,
+                None => return Err(ValueError::py_err("short id index is not built")),
+            };
+            let (txs, missing) = core.reconstruct(index, &short_ids, &positions)
+                .map_err(|err| ValueError::py_err(err))?;
+            let objs: Vec<Option<PyObject>> = txs.into_iter()
+                .map(|tx| tx.map(|tx| tx.obj.clone_ref(py)))
+                .collect();
+            (objs, missing)
+        };
+        for (position, obj) in prefilled {
+            objs[position] = Some(obj);
+        }
+        let objs: Vec<PyObject> = objs.into_iter().map(|obj| obj.to_object(py)).collect();
+        Ok((PyList::new(py, &objs), missing).to_object(py))
+    }
+
+    /// register_keys(hash: bytes, keys: list) -> None
+    /// --
+    ///
+    /// add bloom filter keys (ex. addresses) to unconfirmed tx or orphan
+    fn register_keys(&self, py: Python, hash: &PyBytes, keys: Vec<&PyBytes>) -> PyResult<()> {
+        let hash = U256::from(hash.as_bytes());
+        let keys: Vec<Vec<u8>> = keys.iter().map(|key| key.as_bytes().to_vec()).collect();
+        self.with_pool_mut(py, move |pool| pool.register_keys(&hash, keys))?
+            .map_err(pool_error)
+    }
+
+    /// filter_matching(bloom: PyBloomFilter) -> List[TX]
+    /// --
+    ///
+    /// unconfirmed txs matching light client's filter ordered by priority
+    /// checked keys are hash, spent outpoints (txhash + index 4bytes little endian) and registered keys
+    fn filter_matching(&self, py: Python, bloom: PyRef<PyBloomFilter>) -> PyResult<PyObject> {
+        let objs: Vec<PyObject> = self.pool(py)?
+            .filter_matching(&bloom.bloom)
+            .iter()
+            .map(|tx| tx.obj.clone_ref(py))
+            .collect();
+        Ok(PyList::new(py, &objs).to_object(py))
+    }
+
+    /// sketch(cells: int) -> bytes
+    /// --
+    ///
+    /// invertible bloom lookup table of unconfirmed tx's hashes for peer sync
+    /// cells should be about 1.5 times of expected difference (or more)
+    fn sketch(&self, py: Python, cells: usize) -> PyResult<PyObject> {
+        if cells == 0 || MAX_CELLS < cells {
+            return Err(ValueError::py_err(format!("cells is 1 to {}", MAX_CELLS)));
         }
-        // remove root txs
-        deleted.drain_filter(|_tx| hashs.contains(&_tx.hash)).for_each(drop);
-        // insert all
-        for tx in deleted {
-            assert!(self.push_unconfirmed(tx).is_ok())
+        let buf = self.with_pool(py, move |pool| pool.sketch(cells).to_bytes())?;
+        Ok(PyBytes::new(py, buf.as_slice()).to_object(py))
+    }
+
+    /// reconcile(sketch: bytes) -> Tuple[List[bytes], List[bytes]]
+    /// --
+    ///
+    /// decode difference with peer's sketch and return (only mine, only peer's) hashes
+    /// raise ValueError when difference is too large (retry with bigger sketch) or sketch is crafted
+    fn reconcile(&self, py: Python, sketch: &PyBytes) -> PyResult<PyObject> {
+        let peer = Iblt::from_bytes(sketch.as_bytes())
+            .map_err(|err| ValueError::py_err(err))?;
+        let (mine, theirs) = self.with_pool(py, move |pool| pool.reconcile(&peer))?.map_err(|err| ValueError::py_err(err))?;
+        let mine: Vec<PyObject> = mine.iter().map(|hash| u256_to_bytes(py, hash)).collect();
+        let theirs: Vec<PyObject> = theirs.iter().map(|hash| u256_to_bytes(py, hash)).collect();
+        Ok((PyList::new(py, &mine), PyList::new(py, &theirs)).to_object(py))
+    }
+
+    /// add_callback(event: str, callback: Callable) -> None
+    /// --
+    ///
+    /// register callback fired after pool mutations
+    /// "added": callback(obj) on push, promote orphans, block_disconnected and load
+    /// "removed": callback(obj, reason) reason is confirmed, conflict, expired, evicted or manual
+    /// "reordered": callback(objs) on re-inserted txs after remove, remove_many and block_connected
+    /// pool is not locked while callbacks, raised error is printed and ignored
+    fn add_callback(&self, event: &str, callback: PyObject) -> PyResult<()> {
+        if ![EVENT_ADDED, EVENT_REMOVED, EVENT_REORDERED].contains(&event) {
+            return Err(ValueError::py_err(format!("unknown event \"{}\"", event)));
         }
+        self.callbacks()?.push((event.to_owned(), callback));
+        Ok(())
+    }
+
+    /// clear_callbacks() -> None
+    /// --
+    ///
+    /// unregister all callbacks
+    fn clear_callbacks(&self) -> PyResult<()> {
+        self.callbacks()?.clear();
+        Ok(())
+    }
+
+    /// dump(path: str, encoder: Callable[[TX], bytes]=None) -> int
+    /// --
+    ///
+    /// write unconfirmed txs to file and return dumped count
+    /// raw tx blob is `encoder(obj)` or `bytes(obj)` when encoder is None
+    #[args(encoder="None")]
+    fn dump(&self, py: Python, path: &str, encoder: Option<PyObject>) -> PyResult<usize> {
+        // snapshot, pool is not locked while encoding
+        let (mut entries, objs) = {
+            let core = self.pool(py)?;
+            let objs: Vec<PyObject> = core.iter().map(|tx| tx.obj.clone_ref(py)).collect();
+            (core.dump_entries(), objs)
+        };
+        let builtins = py.import("builtins")?;
+        for (entry, obj) in entries.iter_mut().zip(objs.into_iter()) {
+            let blob = match encoder.as_ref() {
+                Some(encoder) => encoder.call1(py, (obj,))?,
+                None => builtins.call1("bytes", (obj,))?.to_object(py),
+            };
+            let blob: &PyBytes = blob.extract(py)?;
+            entry.blob = blob.as_bytes().to_vec();
+        }
+        let buf = encode_dump(entries.as_slice());
+        std::fs::write(path, buf)
+            .map_err(|err| IOError::py_err(err.to_string()))?;
+        Ok(entries.len())
+    }
+
+    /// load(path: str, decoder: Callable[[bytes], TX], deadline: int) -> int
+    /// --
+    ///
+    /// read unconfirmed txs from dump file and return inserted count (with promoted orphans)
+    /// TX object is rebuilt by `decoder(blob)`, expired entries (deadline < given) are skipped
+    /// deadline is required, same time base as clear_by_deadline
+    /// already inserted, conflicted or over package limits entries are skipped too
+    fn load(&self, py: Python, path: &str, decoder: PyObject, deadline: u32) -> PyResult<usize> {
+        let buf = std::fs::read(path)
+            .map_err(|err| IOError::py_err(err.to_string()))?;
+        let entries = decode_dump(buf.as_slice())
+            .map_err(|err| ValueError::py_err(err))?;
+        let mut loaded: Vec<Unconfirmed<PyObject>> = Vec::with_capacity(entries.len());
+        for entry in entries {
+            if entry.deadline < deadline {
+                continue;
+            }
+            let obj = decoder.call1(py, (PyBytes::new(py, entry.blob.as_slice()),))?;
+            loaded.push(Unconfirmed::new(
+                obj, entry.hash, entry.depends, entry.price, entry.time, entry.deadline, entry.size, entry.inputs));
+        }
+        let (_, changes) = self.with_pool_mut(py, move |pool| pool.insert_batch(loaded))?;
+        self.emit_changes(py, &changes);
+        Ok(changes.added.len())
+    }
+
+    /// stats(buckets: list=None) -> dict
+    /// --
+    ///
+    /// unconfirmed txs statistics
+    /// histogram is list of (lower_price, count, size) by buckets lower bounds
+    /// deltas is dict of txhash -> price delta set by prioritise
+    #[args(buckets="None")]
+    fn stats(&self, py: Python, buckets: Option<Vec<u32>>) -> PyResult<PyObject> {
+        let buckets = buckets.unwrap_or_else(|| HISTOGRAM_BUCKETS.to_vec());
+        let stats = self.pool(py)?.stats(buckets);
+        let histogram: Vec<PyObject> = stats.histogram.iter()
+            .map(|bucket| bucket.to_object(py))
+            .collect();
+
+        let dict = PyDict::new(py);
+        dict.set_item("count", stats.count)?;
+        dict.set_item("total_size", stats.total_size)?;
+        dict.set_item("total_fee", stats.total_fee)?;
+        dict.set_item("min_price", stats.min_price)?;
+        dict.set_item("max_price", stats.max_price)?;
+        dict.set_item("median_price", stats.median_price)?;
+        dict.set_item("oldest_time", stats.oldest_time)?;
+        dict.set_item("depends_count", stats.depends_count)?;
+        dict.set_item("histogram", PyList::new(py, &histogram))?;
+        let deltas = PyDict::new(py);
+        for (hash, delta) in stats.deltas.iter() {
+            deltas.set_item(u256_to_bytes(py, hash), delta)?;
+        }
+        dict.set_item("deltas", deltas)?;
+        Ok(dict.to_object(py))
+    }
+
+    /// estimate_fee(target_blocks: int) -> Optional[int]
+    /// --
+    ///
+    /// estimate price to be confirmed within target blocks
+    /// learned from accepted txs and block_connected, None if not enough data
+    /// txs replaced, evicted, expired or waiting over target blocks are counted as failure
+    fn estimate_fee(&self, py: Python, target_blocks: u32) -> PyResult<Option<u32>> {
+        if target_blocks == 0 || MAX_TARGET_BLOCKS < target_blocks {
+            return Err(ValueError::py_err(
+                format!("target_blocks is 1 to {}", MAX_TARGET_BLOCKS)));
+        }
+        Ok(self.pool(py)?.estimate_fee(target_blocks))
+    }
+
+    /// check_consistency() -> None
+    /// --
+    ///
+    /// verify no duplicate txs, parents before children, non-dependent neighbours
+    /// ordered by price/time and spent index, raise AssertionError when broken
+    fn check_consistency(&self, py: Python) -> PyResult<()> {
+        self.pool(py)?.check_consistency()
+            .map_err(|err| AssertionError::py_err(err))
     }
 
     /// remove_with_depends(hash: bytes) -> int
     /// --
     ///
     /// remove unconfirmed tx with depends and return delete count
-    fn remove_with_depends(&mut self, hash: &PyBytes) -> PyResult<usize> {
+    fn remove_with_depends(&self, py: Python, hash: &PyBytes) -> PyResult<usize> {
         let hash = U256::from(hash.as_bytes());
-        let mut deleted: Vec<Unconfirmed> = Vec::new();
-        self.remove_with_depend_myself(&hash, &mut deleted);
-        match deleted.len() {
-            0 => Err(AssertionError::py_err("not found hash")),
-            count => Ok(count),
-        }
+        let changes = self.with_pool_mut(py, move |pool| pool.remove_with_depends(&hash))?.map_err(pool_error)?;
+        self.emit_changes(py, &changes);
+        Ok(changes.removed.len())
     }
 
     /// list_size_limit(maxsize: int) -> Tuple[TX]
     /// --
     ///
     /// size limit unconfirmed tx's tuple for mining interface
-    fn list_size_limit(&self, py: Python, maxsize: u32) -> PyObject {
+    fn list_size_limit(&self, py: Python, maxsize: u32) -> PyResult<PyObject> {
         // unconfirmed is already sorted by priority
         let mut size = 0;
-        let reader: Vec<PyObject> = self.unconfirmed.iter()
+        let reader: Vec<PyObject> = self.pool(py)?.iter()
             .filter(|tx| {
                 size += tx.size;
                 size < maxsize
             })
             .map(|tx| tx.obj.clone_ref(py))
             .collect();
-        PyTuple::new(py, &reader).to_object(py)
+        Ok(PyTuple::new(py, &reader).to_object(py))
     }
 
     /// list_all_hash() -> Tuple[bytes]
     /// --
     ///
     /// all unconfirmed tx's hash tuple
-    fn list_all_hash(&self, py: Python) -> PyObject {
-        let outputs: Vec<PyObject> = self.unconfirmed
+    fn list_all_hash(&self, py: Python) -> PyResult<PyObject> {
+        let outputs: Vec<PyObject> = self.pool(py)?
+            .iter()
+            .map(|tx| u256_to_bytes(py, &tx.hash))
+            .collect();
+        Ok(PyTuple::new(py, &outputs).to_object(py))
+    }
+
+    /// query(min_price: int=None, max_price: int=None, since: int=None, until: int=None, has_depends: bool=None, limit: int=None, min_size: int=None, max_size: int=None) -> Tuple[TX]
+    /// --
+    ///
+    /// unconfirmed txs matching all conditions ordered by priority
+    /// price is real price (without prioritise delta), time and size bounds are inclusive
+    /// None is no condition, result is at most limit txs
+    #[args(min_price="None", max_price="None", since="None", until="None", has_depends="None", limit="None",
+           min_size="None", max_size="None")]
+    fn query(&self, py: Python, min_price: Option<u32>, max_price: Option<u32>, since: Option<u32>, until: Option<u32>,
+             has_depends: Option<bool>, limit: Option<usize>, min_size: Option<u32>, max_size: Option<u32>) -> PyResult<PyObject> {
+        let query = Query {min_price, max_price, since, until, has_depends, min_size, max_size, limit};
+        let objs: Vec<PyObject> = self.pool(py)?
+            .query(&query)
             .iter()
-            .map(|tx| tx.bytes(py))
+            .map(|tx| tx.obj.clone_ref(py))
             .collect();
-        PyTuple::new(py, &outputs).to_object(py)
+        Ok(PyTuple::new(py, &objs).to_object(py))
+    }
+
+    /// iter_obj(reversed: bool=False) -> PoolIter
+    /// --
+    ///
+    /// live iterator of unconfirmed tx's obj by priority without copy
+    /// raise RuntimeError on next() when the pool is changed while iteration
+    #[args(reversed="false")]
+    fn iter_obj(&self, py: Python, reversed: bool) -> PyResult<PoolIter> {
+        PoolIter::new(py, self.core.clone(), reversed)
     }
 
     /// list_all_obj(reversed: bool) -> Iterator[TX]
     /// --
     ///
     /// all unconfirmed tx's obj tuple
-    fn list_all_obj(&self, py: Python, reversed: bool) -> PyIter {
-        let reader: Vec<PyObject> = self.unconfirmed
+    fn list_all_obj(&self, py: Python, reversed: bool) -> PyResult<PyIter> {
+        let reader: Vec<PyObject> = self.pool(py)?
             .iter()
             .map(|tx| tx.obj.clone_ref(py))
             .collect();
-        PyIter::new(reader, reversed)
+        Ok(PyIter::new(reader, reversed))
+    }
+
+    /// view_all_obj(reversed: bool=False) -> Sequence[TX]
+    /// --
+    ///
+    /// all unconfirmed tx's obj view, supports len, index, slice, `in` and repeated iteration
+    #[args(reversed="false")]
+    fn view_all_obj(&self, py: Python, reversed: bool) -> PyResult<PyView> {
+        let reader: Vec<PyObject> = self.pool(py)?
+            .iter()
+            .map(|tx| tx.obj.clone_ref(py))
+            .collect();
+        Ok(PyView::new(reader, reversed))
     }
 
     /// clear_all() -> None
     /// --
     ///
     /// clear all unconfirmed txs
-    fn clear_all(&mut self) {
-        self.unconfirmed.drain(..)
-            .map(|tx| tx.obj)
-            .for_each(drop);
-        assert_eq!(self.unconfirmed.len(), 0);
+    fn clear_all(&self, py: Python) -> PyResult<()> {
+        let changes = self.with_pool_mut(py, move |pool| pool.clear_all())?;
+        self.emit_changes(py, &changes);
+        Ok(())
     }
 
     /// clear_by_deadline(deadline: int) -> Tuple[TX]
     /// --
     ///
-    /// remove expired unconfirmed txs
-    fn clear_by_deadline(&mut self, py: Python, deadline: u32) -> PyObject {
-        // remove too old tx with depends
-        let mut deleted: Vec<Unconfirmed> = Vec::new();
-        loop {
-            let mut want_delete = None;
-            for tx in self.unconfirmed.iter() {
-                if tx.deadline < deadline {
-                    want_delete = Some(tx.hash.clone());
-                    break;
-                }
-            }
-            match want_delete {
-                Some(hash) => self.remove_with_depend_myself(&hash, &mut deleted),
-                None => break,
-            };
-        }
+    /// remove expired unconfirmed txs and orphans
+    /// finding is O(k log n) by deadline index, removal shifts the pool in O(n)
+    fn clear_by_deadline(&self, py: Python, deadline: u32) -> PyResult<PyObject> {
+        let changes = self.with_pool_mut(py, move |pool| pool.clear_by_deadline(deadline))?;
+        self.emit_changes(py, &changes);
+
         // output expired txs
-        let elements: Vec<PyObject> = deleted
+        let elements: Vec<PyObject> = changes.into_removed(RemoveReason::Expired)
             .into_iter().map(|tx| tx.obj).collect();
-        PyTuple::new(py, &elements).to_object(py)
+        Ok(PyTuple::new(py, &elements).to_object(py))
+    }
+
+    /// expire(max_age_seconds: int, time_offset: int=0) -> Tuple[Tuple[TX, str]]
+    /// --
+    ///
+    /// remove txs and orphans whose time is older than max_age_seconds from now
+    /// now is wall clock minus time_offset (ex. genesis time when tx time is relative)
+    /// finding is O(k log n) by time index, removal shifts the pool in O(n)
+    /// return (obj, reason) tuples of removed txs including depends
+    #[args(time_offset="0")]
+    fn expire(&self, py: Python, max_age_seconds: u32, time_offset: u32) -> PyResult<PyObject> {
+        let now = (get_current_time() as u64).saturating_sub(time_offset as u64);
+        let now = now.min(u32::max_value() as u64) as u32;
+        let changes = self.with_pool_mut(py, move |pool| pool.expire(now, max_age_seconds))?;
+        self.emit_changes(py, &changes);
+
+        let elements: Vec<PyObject> = changes.removed.into_iter()
+            .map(|(tx, reason)| (tx.obj, reason.as_str()).to_object(py))
+            .collect();
+        Ok(PyTuple::new(py, &elements).to_object(py))
     }
 }
 
+#[pyclass]
+pub struct PoolIter {
+    core: Arc<RwLock<TxPool<PyObject>>>,
+    // pool version when created
+    version: u64,
+    // next position from start (or end if reversed)
+    index: usize,
+    length: usize,
+    reversed: bool,
+}
 
-// row level methods only used inner
-impl MemoryPool {
-    // remove unconfirmed tx with depend it
-    fn remove_with_depend_myself(&mut self, hash: &U256, deleted: &mut Vec<Unconfirmed>) {
-        // find position
-        let delete_index = match self.unconfirmed.iter()
-            .position(|tx| hash == &tx.hash) {
-            Some(index) => index,
-            None => return,
-        };
+#[pyproto]
+impl PyIterProtocol for PoolIter {
+    fn __iter__(slf: PyRefMut<Self>) -> PyResult<PyObject> {
+        let py = unsafe { Python::assume_gil_acquired() };
+        Ok(slf.into_py(py))
+    }
 
-        // delete tx
-        deleted.push(self.unconfirmed.remove(delete_index));
-
-        // check depends
-        loop {
-            let mut delete_hash = None;
-            for tx in self.unconfirmed.iter() {
-                if tx.depends.contains(hash) {
-                    delete_hash = Some(tx.hash.clone());
-                    break;
-                }
-            }
-            match delete_hash {
-                Some(hash) => self.remove_with_depend_myself(&hash, deleted),
-                None => break,
-            }
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<PyObject>> {
+        let py = unsafe { Python::assume_gil_acquired() };
+        if slf.length <= slf.index {
+            return Ok(None);
         }
+        let position = if slf.reversed { slf.length - slf.index - 1 } else { slf.index };
+        let obj = {
+            let core = read_pool(py, &slf.core)?;
+            if core.version() != slf.version {
+                return Err(RuntimeError::py_err("mempool changed during iteration"));
+            }
+            core.at(position).map(|tx| tx.obj.clone_ref(py))
+        };
+        slf.index += 1;
+        Ok(obj)
     }
+}
 
-    // push unconfirmed tx with dependency check
-    // return inserted tx's index
-    fn push_unconfirmed(&mut self, unconfirmed: Unconfirmed) -> PyResult<usize> {
-        // most high position depend index
-        let mut depend_index: Option<usize> = None;
-        for (index, tx) in self.unconfirmed.iter().enumerate() {
-            if unconfirmed.depends.contains(&tx.hash) {
-                depend_index = Some(index);
-            }
-            if unconfirmed.hash == tx.hash {
-                return Err(AssertionError::py_err("already inserted tx"));
-            }
-        }
+#[pyproto]
+impl PyMappingProtocol for PoolIter {
+    fn __len__(&self) -> PyResult<usize> {
+        Ok(self.length - self.index)
+    }
 
-        // most low position required index
-        let mut required_index = None;
-        let mut disturbs = Vec::new();
-        for (index, tx) in self.unconfirmed.iter().enumerate().rev() {
-            if tx.depends.contains(&unconfirmed.hash) {
-                required_index = Some(index);
-                // check absolute condition: depend_index < required_index
-                if depend_index.is_some() && depend_index.unwrap() >= index {
-                    disturbs.push(tx.hash.clone());
-                }
-            }
-        }
+    fn __reversed__(&self) -> PyResult<PoolIter> {
+        // full iterator of opposite direction on the same pool version
+        Ok(PoolIter {
+            core: self.core.clone(),
+            version: self.version,
+            index: 0,
+            length: self.length,
+            reversed: !self.reversed,
+        })
+    }
+}
 
-        // exception: with disturbs
-        if 0 < disturbs.len() {
-            // 1. remove disturbs
-            let mut deleted: Vec<Unconfirmed> = Vec::new();
-            for disturb in disturbs {
-                self.remove_with_depend_myself(&disturb, &mut deleted);
-            }
+impl PoolIter {
+    pub fn new(py: Python, core: Arc<RwLock<TxPool<PyObject>>>, reversed: bool) -> PyResult<Self> {
+        let (version, length) = {
+            let pool = read_pool(py, &core)?;
+            (pool.version(), pool.len())
+        };
+        Ok(PoolIter {core, version, index: 0, length, reversed})
+    }
+}
 
-            // 2. push original (not disturbed)
-            let hash = unconfirmed.hash.clone();
-            assert!(self.push_unconfirmed(unconfirmed).is_ok());
+impl MemoryPool {
+    /// shared pool for rust side (ex. block template builder)
+    pub fn shared(&self) -> Arc<RwLock<TxPool<PyObject>>> {
+        self.core.clone()
+    }
 
-            // 3. push deleted disturbs
-            for tx in deleted {
-                assert!(self.push_unconfirmed(tx).is_ok());
-            }
+    // push tx with extra bloom filter keys and fire callbacks
+    fn push_unconfirmed(&self, py: Python, mut unconfirmed: Unconfirmed<PyObject>, keys: Option<Vec<&PyBytes>>)
+        -> PyResult<(Option<usize>, Changes<PyObject>)> {
+        unconfirmed.keys = keys.unwrap_or_default()
+            .iter().map(|key| key.as_bytes().to_vec()).collect();
+        let (index, changes) = self.with_pool_mut(py, move |pool| pool.push(unconfirmed))?.map_err(pool_error)?;
+        self.emit_changes(py, &changes);
+        Ok((index, changes))
+    }
 
-            // 4. find original position
-            let position = self.unconfirmed.iter()
-                .position(|tx| hash == tx.hash);
-            return Ok(position.unwrap())
-        }
+    // read locked pool, wait for writer without GIL
+    fn pool(&self, py: Python) -> PyResult<RwLockReadGuard<TxPool<PyObject>>> {
+        read_pool(py, &self.core)
+    }
 
-        // normal: without disturbs
-        // find best relative condition
-        let mut best_index: Option<usize> = None;
-        for (index, tx) in self.unconfirmed.iter().enumerate() {
-            // absolute conditions
-            // ex
-            //        0 1 2 3 4 5
-            // vec = [a,b,c,d,e,f]
-            //
-            // You can insert positions(2,3,4) when you depends on b(1) and required by e(4)
-            if depend_index.is_some() && index <= depend_index.unwrap() {
-                continue;
-            }
-            if required_index.is_some() && index > required_index.unwrap() {
-                continue;
-            }
+    // run with read locked pool while GIL is released
+    fn with_pool<F, R>(&self, py: Python, f: F) -> PyResult<R>
+        where F: FnOnce(&TxPool<PyObject>) -> R + Send, R: Send {
+        let core = &self.core;
+        py.allow_threads(move || {
+            core.read().map(|pool| f(&pool)).map_err(|_| ())
+        }).map_err(|_| RuntimeError::py_err(POISONED_ERROR))
+    }
 
-            // relative conditions
-            if unconfirmed.price < tx.price {
-                continue;
-            } else if unconfirmed.price == tx.price {
-                if unconfirmed.time >= tx.time {
-                    continue;
-                }
-            }
-            // find
-            if best_index.is_none() {
-                best_index = Some(index);
-                break;
+    // run with write locked pool while GIL is released
+    fn with_pool_mut<F, R>(&self, py: Python, f: F) -> PyResult<R>
+        where F: FnOnce(&mut TxPool<PyObject>) -> R + Send, R: Send {
+        let core = &self.core;
+        py.allow_threads(move || {
+            core.write().map(|mut pool| f(&mut pool)).map_err(|_| ())
+        }).map_err(|_| RuntimeError::py_err(POISONED_ERROR))
+    }
+
+    fn callbacks(&self) -> PyResult<MutexGuard<Vec<(String, PyObject)>>> {
+        self.callbacks.lock().map_err(|_| RuntimeError::py_err(POISONED_ERROR))
+    }
+
+    // call callbacks of the event, errors are printed and ignored
+    // callbacks are copied first, so they can add or clear callbacks
+    fn emit(&self, py: Python, event: &str, args: &[PyObject]) {
+        let callbacks: Vec<PyObject> = match self.callbacks() {
+            Ok(callbacks) => callbacks.iter()
+                .filter(|(_event, _)| _event == event)
+                .map(|(_, callback)| callback.clone_ref(py))
+                .collect(),
+            Err(err) => return err.print(py),
+        };
+        let args = PyTuple::new(py, args);
+        for callback in callbacks {
+            if let Err(err) = callback.call1(py, args) {
+                err.print(py);
             }
         }
+    }
 
-        // minimum index is required_index (or None)
-        if best_index.is_none() {
-            best_index = required_index.clone();
+    // fire callbacks after pool is unlocked
+    fn emit_changes(&self, py: Python, changes: &Changes<PyObject>) {
+        match self.callbacks() {
+            Ok(callbacks) if 0 < callbacks.len() => (),
+            _ => return,
         }
-
-        // insert
-        match best_index {
-            Some(best_index) => {
-                // println!("best {} {:?} {:?}", best_index, depend_index, required_index);
-                self.unconfirmed.insert(best_index, unconfirmed);
-                Ok(best_index)
-            },
-            None => {
-                // println!("last {:?} {:?}", depend_index, required_index);
-                self.unconfirmed.push(unconfirmed);
-                Ok(self.unconfirmed.len())
-            },
+        let (added, reordered) = {
+            let core = match self.pool(py) {
+                Ok(core) => core,
+                Err(err) => return err.print(py),
+            };
+            let added: Vec<PyObject> = changes.added.iter()
+                .filter_map(|hash| core.get(hash))
+                .map(|tx| tx.obj.clone_ref(py))
+                .collect();
+            let reordered: Vec<PyObject> = core.iter()
+                .filter(|tx| changes.reordered.contains(&tx.hash))
+                .map(|tx| tx.obj.clone_ref(py))
+                .collect();
+            (added, reordered)
+        };
+        for (tx, reason) in changes.removed.iter() {
+            self.emit(py, EVENT_REMOVED, &[tx.obj.clone_ref(py), reason.as_str().to_object(py)]);
+        }
+        for obj in added {
+            self.emit(py, EVENT_ADDED, &[obj]);
+        }
+        if 0 < reordered.len() {
+            self.emit(py, EVENT_REORDERED, &[PyTuple::new(py, &reordered).to_object(py)]);
         }
     }
 }
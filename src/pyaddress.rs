@@ -1,42 +1,68 @@
-use crate::utils::python_hash;
-use bc4py_plotter::utils::params2bech;
-use bech32::{Bech32,convert_bits};
+use crate::bech::{encode_address, decode_address, decode, Variant, VariantMap};
+use crate::utils::{python_hash, hash160};
 use pyo3::prelude::*;
 use pyo3::exceptions::ValueError;
-use pyo3::types::{PyBytes, PyType};
+use pyo3::types::{PyAny, PyBytes, PyTuple, PyType};
 use pyo3::PyObjectProtocol;
 use pyo3::class::basic::CompareOp;
+use std::cmp::Ordering;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::Hasher;
-use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering as AtomicOrdering};
 
 
-#[pyclass]
+// versions using bech32m checksum, bits of VariantMap (default all bech32)
+static BECH32M_VERSIONS: AtomicU32 = AtomicU32::new(0);
+
+/// version -> checksum variant map used by python API
+pub fn variant_map() -> VariantMap {
+    VariantMap::from_bits(BECH32M_VERSIONS.load(AtomicOrdering::Relaxed))
+}
+
+/// replace version -> checksum variant map, created PyAddress keep own variant
+pub fn set_variant_map(map: VariantMap) {
+    BECH32M_VERSIONS.store(map.bits(), AtomicOrdering::Relaxed);
+}
+
+
+// module is needed for pickle to find the class by `bc4py_extension.PyAddress`
+#[pyclass(module = "bc4py_extension")]
+#[derive(Clone)]
 pub struct PyAddress {
-    bech: Bech32,
+    hrp: String,
+    ver: u8,
+    identifier: Vec<u8>,
+    variant: Variant,
 }
 
 #[pyproto]
 impl PyObjectProtocol for PyAddress {
     fn __repr__(&self) -> PyResult<String> {
-        Ok(self.bech.to_string())
+        self.to_string().map_err(|err| ValueError::py_err(err))
     }
 
     fn __hash__(&self) -> PyResult<isize> {
         // only check version + identifier
         let mut hasher = DefaultHasher::new();
-        for i in self.bech.data().iter() {
-            hasher.write_u8(i.to_u8());
-        }
+        hasher.write_u8(self.ver);
+        hasher.write(&self.identifier);
         Ok(python_hash(hasher.finish()))
     }
 
+    fn __str__(&self) -> PyResult<String> {
+        self.to_string().map_err(|err| ValueError::py_err(err))
+    }
+
     fn __richcmp__(&self, other: PyRef<'p, Self>, op: CompareOp) -> PyResult<bool> {
         // only check version + identifier
+        let ordering = self.sort_key().cmp(&other.sort_key());
         match op {
-            CompareOp::Eq => Ok(self.bech.data() == other.bech.data()),  // `__eq__`
-            CompareOp::Ne => Ok(self.bech.data() != other.bech.data()),  // `__ne__`
-            _ => Err(ValueError::py_err("not implemented"))
+            CompareOp::Eq => Ok(ordering == Ordering::Equal),  // `__eq__`
+            CompareOp::Ne => Ok(ordering != Ordering::Equal),  // `__ne__`
+            CompareOp::Lt => Ok(ordering == Ordering::Less),  // `__lt__`
+            CompareOp::Le => Ok(ordering != Ordering::Greater),  // `__le__`
+            CompareOp::Gt => Ok(ordering == Ordering::Greater),  // `__gt__`
+            CompareOp::Ge => Ok(ordering != Ordering::Less),  // `__ge__`
         }
     }
 }
@@ -46,12 +72,13 @@ impl PyAddress {
     /// PyAddress.from_string(address: str)
     /// --
     ///
-    /// generate Address obj from string
+    /// generate Address obj from string, checksum variant must match version
+    /// by map of set_bech32m_versions()
     #[classmethod]
     fn from_string(_cls: &PyType, address: &str) -> PyResult<PyAddress> {
-        let bech = Bech32::from_str(address)
-            .map_err(|err| ValueError::py_err(err.to_string()))?;
-        Ok(PyAddress{bech})
+        let (hrp, ver, identifier, variant) = decode_address(address, &variant_map())
+            .map_err(|err| ValueError::py_err(err))?;
+        Ok(PyAddress{hrp, ver, identifier, variant})
     }
 
     /// PyAddress.from_binary(hrp: str, data: bytes)
@@ -64,9 +91,8 @@ impl PyAddress {
         if data.len() != 21 {
             return Err(ValueError::py_err("data is 21 bytes"));
         }
-        let bech = params2bech(hrp, data[0], &data[1..])
-            .map_err(|err| ValueError::py_err(err.to_string()))?;
-        Ok(PyAddress{bech})
+        PyAddress::new(hrp, data[0], &data[1..])
+            .map_err(|err| ValueError::py_err(err))
     }
 
     /// PyAddress.from_param(hrp: str, ver: int, identifier: bytes)
@@ -79,54 +105,143 @@ impl PyAddress {
         if identifier.len() != 20 {
             return Err(ValueError::py_err("identifier is 20 bytes"));
         }
-        let bech = params2bech(hrp, ver, identifier)
-            .map_err(|err| ValueError::py_err(err.to_string()))?;
-        Ok(PyAddress{bech})
+        PyAddress::new(hrp, ver, identifier)
+            .map_err(|err| ValueError::py_err(err))
+    }
+
+    /// PyAddress.from_public_key(hrp: str, ver: int, pubkey: bytes)
+    /// --
+    ///
+    /// generate Address obj from secp256k1 compressed(33b) or uncompressed(65b) public key
+    #[classmethod]
+    fn from_public_key(_cls: &PyType, hrp: &str, ver: u8, pubkey: &PyBytes) -> PyResult<PyAddress> {
+        let pubkey = pubkey.as_bytes();
+        match (pubkey.len(), pubkey.first()) {
+            (33, Some(0x02)) | (33, Some(0x03)) => (),
+            (65, Some(0x04)) => (),
+            _ => return Err(ValueError::py_err("pubkey is compressed 33 bytes or uncompressed 65 bytes")),
+        }
+        let identifier = hash160(pubkey);
+        PyAddress::new(hrp, ver, &identifier)
+            .map_err(|err| ValueError::py_err(err))
     }
 
     #[getter]
     fn hrp(&self) -> String {
-        self.bech.hrp().to_string()
+        self.hrp.clone()
     }
 
     #[getter]
     fn version(&self) -> u8 {
-        self.bech.data()[0].to_u8()
+        self.ver
     }
 
     #[getter]
-    fn string(&self) -> String {
-        self.bech.to_string()
+    fn string(&self) -> PyResult<String> {
+        self.to_string().map_err(|err| ValueError::py_err(err))
+    }
+
+    /// checksum variant "bech32" or "bech32m", selected by version when created
+    #[getter]
+    fn variant(&self) -> &'static str {
+        self.variant.as_str()
     }
 
     /// identifier() -> bytes
     /// --
     ///
     /// return 20bytes identifier
-    fn identifier(&self, py: Python) -> PyResult<PyObject> {
-        let identifier = self.get_identifier()
-            .map_err(|err| ValueError::py_err(err))?;
-        Ok(PyBytes::new(py, &identifier).to_object(py))
+    fn identifier(&self, py: Python) -> PyObject {
+        PyBytes::new(py, &self.identifier).to_object(py)
     }
 
     /// binary() -> bytes
     /// --
     ///
     /// return 21bytes version + identifier
-    fn binary(&self, py: Python) -> PyResult<PyObject> {
-        let identifier = self.get_identifier()
-            .map_err(|err| ValueError::py_err(err))?;
+    fn binary(&self, py: Python) -> PyObject {
         let mut bin = Vec::with_capacity(21);
-        bin.push(self.bech.data()[0].to_u8());
-        bin.extend_from_slice(identifier.as_slice());
-        Ok(PyBytes::new(py, bin.as_slice()).to_object(py))
+        bin.push(self.ver);
+        bin.extend_from_slice(self.identifier.as_slice());
+        PyBytes::new(py, bin.as_slice()).to_object(py)
+    }
+
+    /// __bytes__() -> bytes
+    /// --
+    ///
+    /// same as binary()
+    fn __bytes__(&self, py: Python) -> PyObject {
+        self.binary(py)
+    }
+
+    /// PyAddress._from_state(hrp: str, ver: int, identifier: bytes, variant: str)
+    /// --
+    ///
+    /// restore pickled Address obj with own variant, not checked by current version map
+    #[classmethod]
+    fn _from_state(_cls: &PyType, hrp: &str, ver: u8, identifier: &PyBytes, variant: &str) -> PyResult<PyAddress> {
+        let identifier = identifier.as_bytes();
+        if identifier.len() != 20 {
+            return Err(ValueError::py_err("identifier is 20 bytes"));
+        }
+        let variant = Variant::from_name(variant)
+            .map_err(|err| ValueError::py_err(err))?;
+        PyAddress::with_variant(hrp, ver, identifier, variant)
+            .map_err(|err| ValueError::py_err(err))
+    }
+
+    /// __reduce__() -> tuple
+    /// --
+    ///
+    /// pickle by (PyAddress._from_state, (hrp, ver, identifier, variant))
+    /// variant is carried, so unpickle works in process with other version map
+    fn __reduce__(&self, py: Python) -> PyResult<PyObject> {
+        let from_state = py.get_type::<PyAddress>().getattr("_from_state")?;
+        let args = PyTuple::new(py, &[
+            self.hrp.to_object(py),
+            self.ver.to_object(py),
+            PyBytes::new(py, &self.identifier).to_object(py),
+            self.variant.as_str().to_object(py),
+        ]);
+        Ok(PyTuple::new(py, &[from_state.to_object(py), args.to_object(py)]).to_object(py))
+    }
+
+    fn __copy__(&self) -> PyAddress {
+        self.clone()
+    }
+
+    fn __deepcopy__(&self, _memo: &PyAny) -> PyAddress {
+        // immutable obj, no need to copy deeply
+        self.clone()
     }
 }
 
 impl PyAddress {
+    /// check params are encodable, variant is selected by current map
+    pub fn new(hrp: &str, ver: u8, identifier: &[u8]) -> Result<Self, String> {
+        PyAddress::with_variant(hrp, ver, identifier, variant_map().variant(ver))
+    }
+
+    /// check params are encodable with the variant
+    pub fn with_variant(hrp: &str, ver: u8, identifier: &[u8], variant: Variant) -> Result<Self, String> {
+        let address = encode_address(hrp, ver, identifier, variant)?;
+        // lowercase hrp
+        let (hrp, _data, _variant) = decode(&address)?;
+        Ok(PyAddress{hrp, ver, identifier: identifier.to_vec(), variant})
+    }
+
+    // version + identifier, hrp is ignored same as `__hash__`
+    fn sort_key(&self) -> (u8, &[u8]) {
+        (self.ver, self.identifier.as_slice())
+    }
+
+    /// bech32 or bech32m string by own variant
+    pub fn to_string(&self) -> Result<String, String> {
+        encode_address(&self.hrp, self.ver, &self.identifier, self.variant)
+    }
+
     /// return 20bytes identifier
     pub fn get_identifier(&self) -> Result<Vec<u8>, String> {
-        convert_bits(&self.bech.data()[1..], 5, 8, false)
-            .map_err(|err| err.to_string())
+        Ok(self.identifier.clone())
     }
 }
@@ -0,0 +1,80 @@
+use crate::bloom::BloomFilter;
+use pyo3::prelude::*;
+use pyo3::exceptions::ValueError;
+use pyo3::types::{PyBytes, PyType};
+use pyo3::PyObjectProtocol;
+
+
+#[pyclass]
+pub struct PyBloomFilter {
+    pub bloom: BloomFilter,
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyBloomFilter {
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("<PyBloomFilter size={} hash_funcs={} tweak={}>",
+                   self.bloom.size(), self.bloom.hash_funcs(), self.bloom.tweak()))
+    }
+}
+
+#[pymethods]
+impl PyBloomFilter {
+    #[new]
+    #[args(tweak="0")]
+    fn new(elements: usize, fp_rate: f64, tweak: u32) -> PyResult<Self> {
+        let bloom = BloomFilter::new(elements, fp_rate, tweak)
+            .map_err(|err| ValueError::py_err(err))?;
+        Ok(PyBloomFilter {bloom})
+    }
+
+    /// PyBloomFilter.from_bytes(data: bytes)
+    /// --
+    ///
+    /// restore filter serialized by to_bytes()
+    #[classmethod]
+    fn from_bytes(_cls: &PyType, data: &PyBytes) -> PyResult<PyBloomFilter> {
+        let bloom = BloomFilter::from_bytes(data.as_bytes())
+            .map_err(|err| ValueError::py_err(err))?;
+        Ok(PyBloomFilter {bloom})
+    }
+
+    #[getter]
+    fn size(&self) -> usize {
+        self.bloom.size()
+    }
+
+    #[getter]
+    fn hash_funcs(&self) -> u32 {
+        self.bloom.hash_funcs()
+    }
+
+    #[getter]
+    fn tweak(&self) -> u32 {
+        self.bloom.tweak()
+    }
+
+    /// insert(data: bytes) -> None
+    /// --
+    ///
+    /// add element to filter
+    fn insert(&mut self, data: &PyBytes) {
+        self.bloom.insert(data.as_bytes());
+    }
+
+    /// contains(data: bytes) -> bool
+    /// --
+    ///
+    /// element may be inserted (false positive) or never inserted
+    fn contains(&self, data: &PyBytes) -> bool {
+        self.bloom.contains(data.as_bytes())
+    }
+
+    /// to_bytes() -> bytes
+    /// --
+    ///
+    /// serialize filter to send peer
+    fn to_bytes(&self, py: Python) -> PyObject {
+        PyBytes::new(py, self.bloom.to_bytes().as_slice()).to_object(py)
+    }
+}
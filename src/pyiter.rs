@@ -1,40 +1,124 @@
 use pyo3::prelude::*;
-use pyo3::exceptions::AssertionError;
-use pyo3::PyIterProtocol;
+use pyo3::exceptions::{IndexError, TypeError};
+use pyo3::types::PySlice;
+use pyo3::class::basic::CompareOp;
+use pyo3::{PyIterProtocol, PyMappingProtocol, PySequenceProtocol, PyTryFrom};
+use std::os::raw::c_long;
+use std::sync::Arc;
 
 
+/// iterator of objects, `iter(it)` returns itself
+/// exhausted iterator keeps raising StopIteration
 #[pyclass]
 pub struct PyIter {
-    finish: bool,
-    reader: Vec<PyObject>,
+    reader: Arc<Vec<PyObject>>,
     reversed: bool,
+    // next position of cursor
+    index: usize,
 }
 
 #[pyproto]
-impl<T> PyIterProtocol for PyIter {
+impl PyIterProtocol for PyIter {
     fn __iter__(slf: PyRefMut<Self>) -> PyResult<PyObject> {
         let py = unsafe { Python::assume_gil_acquired() };
         Ok(slf.into_py(py))
     }
 
     fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<PyObject>> {
-        if slf.finish {
-            Err(AssertionError::py_err("iter is already used"))
-        } else {
-            if slf.reader.len() == 0 {
-                slf.finish = true;
-                Ok(None)
-            } else if slf.reversed {
-                Ok(slf.reader.pop())  // get from last (reversed)
-            } else {
-                Ok(Some(slf.reader.remove(0)))  // get from top
-            }
+        let py = unsafe { Python::assume_gil_acquired() };
+        let obj = get(&slf.reader, slf.reversed, slf.index).map(|obj| obj.clone_ref(py));
+        if obj.is_some() {
+            slf.index += 1;
         }
+        Ok(obj)
     }
 }
 
 impl PyIter {
     pub fn new(reader: Vec<PyObject>, reversed: bool) -> Self {
-        PyIter {finish: false, reader, reversed }
+        PyIter {reader: Arc::new(reader), reversed, index: 0}
+    }
+}
+
+
+/// sequence view of objects with len, getitem (int or slice) and contains
+/// `iter(view)` returns fresh PyIter, so iteration is restartable
+#[pyclass]
+pub struct PyView {
+    reader: Arc<Vec<PyObject>>,
+    reversed: bool,
+}
+
+#[pyproto]
+impl PyIterProtocol for PyView {
+    fn __iter__(slf: PyRefMut<Self>) -> PyResult<PyIter> {
+        Ok(PyIter {reader: slf.reader.clone(), reversed: slf.reversed, index: 0})
+    }
+}
+
+#[pyproto]
+impl PyMappingProtocol for PyView {
+    fn __len__(&self) -> PyResult<usize> {
+        Ok(self.reader.len())
+    }
+
+    fn __getitem__(&self, key: &PyAny) -> PyResult<PyObject> {
+        let py = key.py();
+        if let Ok(slice) = <PySlice as PyTryFrom>::try_from(key) {
+            // copy selected objects to new view
+            let indices = slice.indices(self.reader.len() as c_long)?;
+            let mut reader = Vec::with_capacity(indices.slicelength as usize);
+            let mut position = indices.start;
+            for _ in 0..indices.slicelength {
+                reader.push(get(&self.reader, self.reversed, position as usize).unwrap().clone_ref(py));
+                position += indices.step;
+            }
+            return Ok(PyView::new(reader, false).into_py(py));
+        }
+        let index: isize = key.extract()
+            .map_err(|_| TypeError::py_err("indices must be integers or slices"))?;
+        let index = if index < 0 { index + self.reader.len() as isize } else { index };
+        if index < 0 {
+            return Err(IndexError::py_err("index out of range"));
+        }
+        match get(&self.reader, self.reversed, index as usize) {
+            Some(obj) => Ok(obj.clone_ref(py)),
+            None => Err(IndexError::py_err("index out of range")),
+        }
+    }
+
+    fn __reversed__(&self) -> PyResult<PyIter> {
+        Ok(PyIter {reader: self.reader.clone(), reversed: !self.reversed, index: 0})
+    }
+}
+
+#[pyproto]
+impl PySequenceProtocol for PyView {
+    fn __contains__(&self, item: &PyAny) -> PyResult<bool> {
+        let py = item.py();
+        for obj in self.reader.iter() {
+            if item.rich_compare(obj, CompareOp::Eq)?.is_true(py)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl PyView {
+    pub fn new(reader: Vec<PyObject>, reversed: bool) -> Self {
+        PyView {reader: Arc::new(reader), reversed}
+    }
+}
+
+// object at the view position
+fn get(reader: &[PyObject], reversed: bool, index: usize) -> Option<&PyObject> {
+    if reversed {
+        match reader.len().checked_sub(index + 1) {
+            Some(index) => reader.get(index),
+            None => None,
+        }
+    } else {
+        reader.get(index)
     }
 }
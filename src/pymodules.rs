@@ -1,13 +1,15 @@
 use super::bc4py_plotter::pochash::{generator,HASH_LOOP_COUNT,HASH_LENGTH};
 use super::bc4py_plotter::utils::*;
-use crate::pyaddress::PyAddress;
-use crate::mempool::MemoryPool;
+use crate::pyaddress::{PyAddress, variant_map, set_variant_map};
+use crate::bech::{encode_address, decode_address, diagnose, VariantMap};
+use crate::pybloom::PyBloomFilter;
+use crate::mempool::{MemoryPool, PackageLimitError};
 use crate::workhash::*;
 use crate::utils::{bytes_to_u32, u32_to_bytes, sha256double};
 use blake2b_simd::blake2b;
 use pyo3::prelude::*;
 use pyo3::exceptions::ValueError;
-use pyo3::types::{PyBytes,PyTuple,PyList};
+use pyo3::types::{PyBytes,PyTuple,PyList,PyDict};
 use pyo3::wrap_pyfunction;
 use std::time::Instant;
 
@@ -207,16 +209,17 @@ fn multi_seek(_py: Python<'_>, dir: &str, previous_hash: &PyBytes, target: &PyBy
 /// --
 ///
 /// get bech32 address from params(hrp, version, identifier)
+/// bech32m checksum is used for versions set by set_bech32m_versions()
 #[pyfunction]
 fn bech2address(_py: Python<'_>, hrp: &str, ver: u8, identifier: &PyBytes)
     -> PyResult<PyObject> {
     // (hrp, ver, identifier) -> bech address
     let identifier = identifier.as_bytes();
-    let bech = match params2bech(hrp, ver, identifier) {
-        Ok(bech) => bech,
-        Err(err) => return Err(ValueError::py_err(err.to_string()))
+    let address = match encode_address(hrp, ver, identifier, variant_map().variant(ver)) {
+        Ok(address) => address,
+        Err(err) => return Err(ValueError::py_err(err))
     };
-    Ok(bech.to_string().to_object(_py))
+    Ok(address.to_object(_py))
 }
 
 
@@ -224,12 +227,13 @@ fn bech2address(_py: Python<'_>, hrp: &str, ver: u8, identifier: &PyBytes)
 /// --
 ///
 /// get params(hrp, version, identifier) from address
+/// checksum variant must match version by set_bech32m_versions()
 #[pyfunction]
 fn address2bech(_py: Python<'_>, addr: &str) -> PyResult<PyObject> {
     // bech address -> (hrp, ver, identifier)
-    let (hrp, ver, identifier) = match addr2params(addr) {
-        Ok((hrp, ver, identifier)) => (hrp, ver, identifier),
-        Err(err) => return Err(ValueError::py_err(err.to_string()))
+    let (hrp, ver, identifier) = match decode_address(addr, &variant_map()) {
+        Ok((hrp, ver, identifier, _variant)) => (hrp, ver, identifier),
+        Err(err) => return Err(ValueError::py_err(err))
     };
     Ok(PyTuple::new(_py,&[
         hrp.to_object(_py),
@@ -239,6 +243,51 @@ fn address2bech(_py: Python<'_>, addr: &str) -> PyResult<PyObject> {
 }
 
 
+/// diagnose_address(addr:str, hrp:str=None) -> dict
+/// --
+///
+/// find likely wrong chars of address, suggestion is corrected address when unambiguous
+/// keys: error, hrp, variant, mixed_case, invalid_chars, wrong_hrp, error_positions, suggestion
+#[pyfunction(hrp="None")]
+fn diagnose_address(_py: Python<'_>, addr: &str, hrp: Option<&str>) -> PyResult<PyObject> {
+    let diagnosis = diagnose(addr, hrp, &variant_map());
+    let dict = PyDict::new(_py);
+    dict.set_item("error", diagnosis.error)?;
+    dict.set_item("hrp", diagnosis.hrp)?;
+    dict.set_item("variant", diagnosis.variant.map(|variant| variant.as_str()))?;
+    dict.set_item("mixed_case", diagnosis.mixed_case)?;
+    dict.set_item("invalid_chars", diagnosis.invalid_chars)?;
+    dict.set_item("wrong_hrp", diagnosis.wrong_hrp)?;
+    dict.set_item("error_positions", diagnosis.error_positions)?;
+    dict.set_item("suggestion", diagnosis.suggestion)?;
+    Ok(dict.to_object(_py))
+}
+
+
+/// set_bech32m_versions(versions: list) -> None
+/// --
+///
+/// address versions (0 to 31) which use bech32m checksum, others use bech32
+/// default is empty, so all existing addresses are bech32
+/// set once at start up, PyAddress objects created before keep their variant
+#[pyfunction]
+fn set_bech32m_versions(_py: Python<'_>, versions: Vec<u8>) -> PyResult<()> {
+    let map = VariantMap::new(&versions).map_err(|err| ValueError::py_err(err))?;
+    set_variant_map(map);
+    Ok(())
+}
+
+
+/// get_bech32m_versions() -> list
+/// --
+///
+/// address versions which use bech32m checksum in ascending order
+#[pyfunction]
+fn get_bech32m_versions(_py: Python<'_>) -> Vec<u8> {
+    variant_map().bech32m_versions()
+}
+
+
 /// This module is a python module implemented in Rust.
 #[pymodule]
 fn bc4py_extension(_py: Python, m: &PyModule) -> PyResult<()> {
@@ -253,7 +302,12 @@ fn bc4py_extension(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_wrapped(wrap_pyfunction!(multi_seek))?;
     m.add_wrapped(wrap_pyfunction!(bech2address))?;
     m.add_wrapped(wrap_pyfunction!(address2bech))?;
+    m.add_wrapped(wrap_pyfunction!(diagnose_address))?;
+    m.add_wrapped(wrap_pyfunction!(set_bech32m_versions))?;
+    m.add_wrapped(wrap_pyfunction!(get_bech32m_versions))?;
     m.add_class::<PyAddress>()?;
+    m.add_class::<PyBloomFilter>()?;
     m.add_class::<MemoryPool>()?;
+    m.add("PackageLimitError", _py.get_type::<PackageLimitError>())?;
     Ok(())
 }
@@ -1,5 +1,4 @@
-use std::mem::transmute;
-use sha2::{Sha256, Digest};
+pub use bc4py_core::utils::{u32_to_bytes, bytes_to_u32, sha256double, hash160};
 use std::convert::TryFrom;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -7,27 +6,6 @@ use std::time::{SystemTime, UNIX_EPOCH};
 const MAX_POINTER_INT: u64 = usize::max_value() as u64;
 
 
-#[inline]
-pub fn u32_to_bytes(i: u32) -> [u8;4] {
-    unsafe { transmute(i.to_le()) }
-}
-
-#[inline]
-pub fn bytes_to_u32(bytes: &[u8]) -> u32 {
-    let mut tmp= [0u8;4];
-    for (a, b) in tmp.iter_mut().zip(bytes.iter()) {
-        *a = *b
-    }
-    unsafe {transmute::<[u8; 4], u32>(tmp)}
-}
-
-#[inline]
-pub fn sha256double(b: &[u8]) -> Vec<u8> {
-    let hash = Sha256::digest(b);
-    let hash = Sha256::digest(hash.as_slice());
-    hash.to_vec()
-}
-
 #[inline]
 pub fn work_check(work: &[u8], target: &[u8]) -> bool {
     // "hash < target" => true
@@ -0,0 +1,80 @@
+import unittest
+from bc4py_extension import MemoryPool
+
+
+def txhash(i):
+    return i.to_bytes(32, 'big')
+
+
+class TestCallbacks(unittest.TestCase):
+
+    def setUp(self):
+        self.pool = MemoryPool()
+        self.events = list()
+
+    def push(self, i, depends=(), price=1):
+        return self.pool.push("tx%d" % i, txhash(i), [txhash(d) for d in depends], price, i, 0, 100)
+
+    def test_added_removed_reordered(self):
+        self.pool.add_callback("added", lambda obj: self.events.append(("added", obj)))
+        self.pool.add_callback("removed", lambda obj, reason: self.events.append(("removed", obj, reason)))
+        self.pool.add_callback("reordered", lambda objs: self.events.append(("reordered", objs)))
+        self.push(1)
+        self.push(2, depends=[1])
+        self.assertEqual(self.events, [("added", "tx1"), ("added", "tx2")])
+        self.events.clear()
+        self.pool.remove(txhash(1))
+        self.assertEqual(self.events, [("removed", "tx1", "manual"), ("reordered", ("tx2",))])
+
+    def test_error_is_ignored(self):
+        def raise_error(obj):
+            raise ValueError("ignored")
+        self.pool.add_callback("added", raise_error)
+        self.pool.add_callback("added", lambda obj: self.events.append(obj))
+        self.push(1)
+        self.assertEqual(self.events, ["tx1"])
+        self.assertEqual(self.pool.length(), 1)
+
+    def test_modify_callbacks_in_callback(self):
+        # callbacks are copied before dispatch, pool is not locked
+        def clear(obj):
+            self.events.append(obj)
+            self.pool.clear_callbacks()
+            self.events.append(self.pool.length())
+        self.pool.add_callback("added", clear)
+        self.push(1)
+        self.push(2)
+        self.assertEqual(self.events, ["tx1", 1])
+
+    def test_unknown_event(self):
+        with self.assertRaises(ValueError):
+            self.pool.add_callback("unknown", print)
+
+
+class TestPoolIter(unittest.TestCase):
+
+    def setUp(self):
+        self.pool = MemoryPool()
+        for i in range(1, 4):
+            self.pool.push("tx%d" % i, txhash(i), [], 10 - i, i, 0, 100)
+
+    def test_iterate(self):
+        it = self.pool.iter_obj()
+        self.assertIs(iter(it), it)
+        self.assertEqual(len(it), 3)
+        self.assertEqual(list(it), ["tx1", "tx2", "tx3"])
+        self.assertEqual(list(self.pool.iter_obj(reversed=True)), ["tx3", "tx2", "tx1"])
+        self.assertEqual(list(reversed(self.pool.iter_obj())), ["tx3", "tx2", "tx1"])
+
+    def test_changed_while_iteration(self):
+        it = self.pool.iter_obj()
+        self.assertEqual(next(it), "tx1")
+        self.pool.push("tx4", txhash(4), [], 1, 4, 0, 100)
+        with self.assertRaises(RuntimeError):
+            next(it)
+        # new iterator after the change is fine
+        self.assertEqual(len(list(self.pool.iter_obj())), 4)
+
+
+if __name__ == '__main__':
+    unittest.main()
@@ -0,0 +1,56 @@
+import copy
+import pickle
+import unittest
+from bc4py_extension import PyAddress, set_bech32m_versions, get_bech32m_versions
+
+
+GENERATOR_PUBKEY = bytes.fromhex("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+
+
+class TestPyAddress(unittest.TestCase):
+
+    def setUp(self):
+        self.address = PyAddress.from_public_key("test", 0, GENERATOR_PUBKEY)
+
+    def test_pickle(self):
+        for protocol in range(pickle.HIGHEST_PROTOCOL + 1):
+            data = pickle.dumps(self.address, protocol)
+            restored = pickle.loads(data)
+            self.assertIsInstance(restored, PyAddress)
+            self.assertEqual(restored, self.address)
+            self.assertEqual(restored.string, self.address.string)
+            self.assertEqual(restored.hrp, self.address.hrp)
+
+    def test_copy(self):
+        self.assertEqual(copy.copy(self.address).string, self.address.string)
+        self.assertEqual(copy.deepcopy([self.address])[0].string, self.address.string)
+
+    def test_identifier(self):
+        self.assertEqual(self.address.identifier().hex(), "751e76e8199196d454941c45d1b3a323f1433bd6")
+
+    def test_variant(self):
+        self.assertEqual(get_bech32m_versions(), [])
+        self.assertEqual(self.address.variant, "bech32")
+        set_bech32m_versions([1])
+        try:
+            self.assertEqual(get_bech32m_versions(), [1])
+            address = PyAddress.from_public_key("test", 1, GENERATOR_PUBKEY)
+            self.assertEqual(address.variant, "bech32m")
+            self.assertEqual(PyAddress.from_string(address.string), address)
+            data = pickle.dumps(address)
+            # created before keep own variant
+            self.assertEqual(self.address.variant, "bech32")
+        finally:
+            set_bech32m_versions([])
+        # unpickle in process with other map (ex. multiprocessing spawn worker)
+        restored = pickle.loads(data)
+        self.assertEqual(restored.variant, "bech32m")
+        self.assertEqual(restored.string, address.string)
+        with self.assertRaises(ValueError):
+            PyAddress.from_string(address.string)
+        with self.assertRaises(ValueError):
+            set_bech32m_versions([32])
+
+
+if __name__ == '__main__':
+    unittest.main()